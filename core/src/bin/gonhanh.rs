@@ -0,0 +1,337 @@
+//! Command-line front end to the core engine
+//!
+//! Reproduces bug reports without a GUI:
+//!
+//! ```text
+//! gonhanh convert [--method M] [--reverse]        keystrokes on stdin → Vietnamese
+//! gonhanh replay [--method M] [--config F] [LOG]  print each key's Result
+//! gonhanh validate [--traditional] [FILE]         report invalid syllables
+//! gonhanh shortcuts import FILE...                check shortcut files
+//! gonhanh shortcuts export [FILE...]              merge files in the native format
+//! ```
+//!
+//! A key log has one key per line: a character, a key name (`space`, `tab`,
+//! `return`, `enter`, `esc`, `delete`, `left`, `right`, `up`, `down`) or a
+//! hex keycode (`0x0e`), followed by any of `caps`, `ctrl`, `shift`. `#`
+//! starts a comment, so the `#` key is `3 shift`. Logs recorded by the
+//! engine (`Engine::start_recording`) are re-run instead, printing the
+//! events whose result changed.
+//!
+//! Exit status: 0 on success, 1 when input is invalid, 2 on usage errors.
+
+use gonhanh_core::convert;
+use gonhanh_core::data::keys;
+use gonhanh_core::engine::config::EngineConfig;
+use gonhanh_core::engine::shortcut::{self, ShortcutTable};
+use gonhanh_core::engine::{Action, Edit, Engine};
+use gonhanh_core::spell;
+use gonhanh_core::utils;
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+Usage: gonhanh <command> [options]
+
+Commands:
+  convert [--method M] [--reverse]         Keystrokes on stdin to Vietnamese (--reverse: back)
+  replay [--method M] [--config F] [LOG]   Type a key log, printing each Result
+                                           (recorded logs: print changed results)
+  validate [--traditional] [FILE]          Report invalid syllables
+  shortcuts import FILE...                 Check shortcut files (native or UniKey)
+  shortcuts export [FILE...]               Print shortcuts in the native format
+
+Methods: telex (default), vni, viqr. Input is stdin when FILE is omitted.";
+
+/// Command-line failure
+enum Error {
+    /// Bad arguments: print usage
+    Usage(String),
+    /// Unreadable or invalid input
+    Input(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Input(e.to_string())
+    }
+}
+
+/// Options shared by the commands
+#[derive(Default)]
+struct Options {
+    config: EngineConfig,
+    reverse: bool,
+    traditional: bool,
+    files: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, Error> {
+        let mut opts = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| Error::Usage(format!("{} needs a value", arg)))
+            };
+            match arg.as_str() {
+                "--method" | "-m" => {
+                    let method = value()?;
+                    opts.config
+                        .set("method", method)
+                        .map_err(|e| Error::Usage(format!("{}: {}", e, method)))?;
+                }
+                "--config" | "-c" => {
+                    let path = value()?;
+                    opts.config = EngineConfig::parse(&read(path)?)
+                        .map_err(|e| Error::Input(format!("{}: {}", path, e)))?;
+                }
+                "--reverse" | "-r" => opts.reverse = true,
+                "--traditional" => opts.traditional = true,
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(Error::Usage(format!("unknown option {}", arg)))
+                }
+                _ => opts.files.push(arg.clone()),
+            }
+        }
+        Ok(opts)
+    }
+
+    /// Contents of the only input file, or stdin
+    fn input(&self) -> Result<String, Error> {
+        match self.files.as_slice() {
+            [] => read("-"),
+            [path] => read(path),
+            _ => Err(Error::Usage("expected at most one file".to_string())),
+        }
+    }
+}
+
+/// Read a file as UTF-8 (`-` is stdin)
+fn read(path: &str) -> Result<String, Error> {
+    let text = if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| Error::Input(format!("{}: {}", path, e)))?
+    };
+    Ok(text)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((command, rest)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    };
+    let result = match command.as_str() {
+        "convert" => Options::parse(rest).and_then(|o| run_convert(&o)),
+        "replay" => Options::parse(rest).and_then(|o| run_replay(&o)),
+        "validate" => Options::parse(rest).and_then(|o| run_validate(&o)),
+        "shortcuts" => run_shortcuts(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        _ => Err(Error::Usage(format!("unknown command {}", command))),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(Error::Input(e)) => {
+            eprintln!("gonhanh: {}", e);
+            ExitCode::from(1)
+        }
+        Err(Error::Usage(e)) => {
+            eprintln!("gonhanh: {}\n\n{}", e, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// ============================================================
+// Commands (Ok(false) = input has problems)
+// ============================================================
+
+fn run_convert(opts: &Options) -> Result<bool, Error> {
+    let text = opts.input()?;
+    let out = if opts.reverse {
+        convert::to_keystrokes(&text, opts.config.method)
+            .ok_or_else(|| Error::Usage("--reverse: only telex and vni".to_string()))?
+    } else {
+        convert::compose(&text, &opts.config)
+    };
+    io::stdout().write_all(out.as_bytes())?;
+    Ok(true)
+}
+
+fn run_replay(opts: &Options) -> Result<bool, Error> {
+    let log = opts.input()?;
+    let mut e = Engine::new();
+    let mut out = io::stdout().lock();
+    // Recorded logs carry their options
+    if log.starts_with("#gonhanh-keylog") {
+        let mismatches = e.replay(&log).map_err(|e| Error::Input(e.to_string()))?;
+        for m in &mismatches {
+            writeln!(out, "{}", m)?;
+        }
+        return Ok(mismatches.is_empty());
+    }
+    e.apply_config(&opts.config);
+    let mut screen = String::new();
+
+    for (idx, line) in log.lines().enumerate() {
+        let entry = line.split('#').next().unwrap_or("").trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let (key, caps, ctrl, shift) =
+            parse_key(entry).map_err(|e| Error::Input(format!("line {}: {}", idx + 1, e)))?;
+
+        let edit = Edit::from(&e.on_key_ext(key, caps, ctrl, shift));
+        if edit.action == Action::None as u8 {
+            writeln!(out, "{:<12} none", entry)?;
+        } else {
+            let consumed = if edit.key_consumed { " consumed" } else { "" };
+            writeln!(
+                out,
+                "{:<12} send bs={} {:?}{}",
+                entry, edit.backspace, edit.text, consumed
+            )?;
+        }
+        if convert::apply_edit(&mut screen, &edit, key, shift) && !ctrl {
+            type_key(&mut screen, key, caps, shift);
+        }
+    }
+    writeln!(out, "screen: {:?}", screen)?;
+    Ok(true)
+}
+
+fn run_validate(opts: &Options) -> Result<bool, Error> {
+    let text = opts.input()?;
+    let mut out = io::stdout().lock();
+    let mut valid = true;
+
+    for (idx, line) in text.lines().enumerate() {
+        for span in spell::check(line, !opts.traditional) {
+            let column = line[..span.start].chars().count() + 1;
+            writeln!(
+                out,
+                "{}:{}: {} ({:?})",
+                idx + 1,
+                column,
+                &line[span.start..span.end],
+                span.reason
+            )?;
+            valid = false;
+        }
+    }
+    Ok(valid)
+}
+
+fn run_shortcuts(args: &[String]) -> Result<bool, Error> {
+    let Some((action, files)) = args.split_first() else {
+        return Err(Error::Usage("expected import or export".to_string()));
+    };
+    match action.as_str() {
+        "import" if files.is_empty() => Err(Error::Usage("expected a file".to_string())),
+        "import" => {
+            let mut valid = true;
+            for path in files {
+                match shortcut::parse(&read(path)?) {
+                    Ok(shortcuts) => println!("{}: {} shortcuts", path, shortcuts.len()),
+                    Err(e) => {
+                        eprintln!("{}: {}", path, e);
+                        valid = false;
+                    }
+                }
+            }
+            Ok(valid)
+        }
+        "export" => {
+            // Nothing to merge: the built-in shortcuts
+            let mut table = if files.is_empty() {
+                ShortcutTable::with_all_defaults()
+            } else {
+                ShortcutTable::new()
+            };
+            for path in files {
+                table
+                    .import(&read(path)?)
+                    .map_err(|e| Error::Input(format!("{}: {}", path, e)))?;
+            }
+            io::stdout().write_all(table.export().as_bytes())?;
+            Ok(true)
+        }
+        _ => Err(Error::Usage(format!(
+            "unknown shortcuts command {}",
+            action
+        ))),
+    }
+}
+
+// ============================================================
+// Key log
+// ============================================================
+
+/// Parse a key log entry into (key, caps, ctrl, shift)
+fn parse_key(entry: &str) -> Result<(u16, bool, bool, bool), String> {
+    let mut fields = entry.split_whitespace();
+    let name = fields.next().unwrap_or("");
+    let mut chars = name.chars();
+    let (key, mut caps, mut shift) = match (chars.next(), chars.next()) {
+        (Some(c), None) => {
+            utils::char_to_key_ext(c).ok_or_else(|| format!("no key for {:?}", c))?
+        }
+        _ => (key_code(name)?, false, false),
+    };
+    let mut ctrl = false;
+    for flag in fields {
+        match flag {
+            "caps" => caps = true,
+            "ctrl" => ctrl = true,
+            "shift" => shift = true,
+            _ => return Err(format!("unknown modifier {}", flag)),
+        }
+    }
+    Ok((key, caps, ctrl, shift))
+}
+
+/// Keycode of a key name or hex keycode
+fn key_code(name: &str) -> Result<u16, String> {
+    Ok(match name {
+        "space" => keys::SPACE,
+        "tab" => keys::TAB,
+        "return" => keys::RETURN,
+        "enter" => keys::ENTER,
+        "esc" => keys::ESC,
+        "delete" => keys::DELETE,
+        "left" => keys::LEFT,
+        "right" => keys::RIGHT,
+        "up" => keys::UP,
+        "down" => keys::DOWN,
+        _ => name
+            .strip_prefix("0x")
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            .ok_or_else(|| format!("unknown key {}", name))?,
+    })
+}
+
+/// Apply a key the engine passed through to `screen`
+fn type_key(screen: &mut String, key: u16, caps: bool, shift: bool) {
+    match key {
+        keys::DELETE => {
+            screen.pop();
+        }
+        keys::ENTER => screen.push('\n'),
+        _ => screen.extend(utils::key_to_char_ext(key, caps, shift).or_else(|| {
+            // Punctuation and whitespace: the char typed by this key
+            ['\t', '\n']
+                .into_iter()
+                .chain(' '..='~')
+                .find(|&c| utils::char_to_key_ext(c) == Some((key, caps, shift)))
+        })),
+    }
+}
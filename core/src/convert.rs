@@ -0,0 +1,204 @@
+//! Batch text conversion
+//!
+//! Raw keystroke text (chat exports, SMS gateways) to Vietnamese and back:
+//!
+//! ```
+//! use gonhanh_core::convert;
+//! use gonhanh_core::engine::config::EngineConfig;
+//!
+//! let text = convert::compose("tieengs Vieetj, ddaays!", &EngineConfig::default());
+//! assert_eq!(text, "tiếng Việt, đấy!");
+//! let keystrokes = convert::to_keystrokes("tiếng Việt", 1);
+//! assert_eq!(keystrokes.as_deref(), Some("tie6ng1 Vie6t5"));
+//! assert_eq!(convert::to_keystrokes("tiếng Việt", 2), None);
+//! ```
+
+use crate::data::chars::{mark, parse_char, tone, ParsedChar};
+use crate::data::keys;
+use crate::engine::config::EngineConfig;
+use crate::engine::{Action, Edit, Engine};
+use crate::utils;
+
+/// Type `text` into a fresh engine with `config`, returning what ends up on screen
+///
+/// Every char is a key press, so words end at spaces and punctuation as
+/// when typing. Chars with no key (already composed text, emoji) pass
+/// through and end the word.
+pub fn compose(text: &str, config: &EngineConfig) -> String {
+    let mut e = Engine::new();
+    e.apply_config(config);
+    let mut screen = String::with_capacity(text.len() * 2);
+
+    for ch in text.chars() {
+        // Backspace and ESC are editing keys, not text
+        let key = match ch {
+            '\x08' | '\x1b' => None,
+            _ => utils::char_to_key_ext(ch),
+        };
+        let Some((key, caps, shift)) = key else {
+            e.clear_all();
+            screen.push(ch);
+            continue;
+        };
+
+        let edit = e.on_key_edit(key, caps, false, shift);
+        if apply_edit(&mut screen, &edit, key, shift) {
+            screen.push(ch);
+        }
+    }
+    screen
+}
+
+/// Apply the engine's edit for `key` to `screen`, as the host does
+///
+/// Returns whether the host then types the key itself: when the engine
+/// passes it through, or after a restore for break keys (a sent space is
+/// part of the edit).
+pub fn apply_edit(screen: &mut String, edit: &Edit, key: u16, shift: bool) -> bool {
+    if edit.action != Action::Send as u8 {
+        return true;
+    }
+    for _ in 0..edit.backspace {
+        screen.pop();
+    }
+    screen.push_str(&edit.text);
+    key != keys::SPACE && keys::is_break_ext(key, shift) && !edit.key_consumed
+}
+
+/// Shortest keystrokes typing `text` with `method` (0=Telex, 1=VNI)
+///
+/// Diacritics follow their vowel ("aa", "a6") and the tone mark key ends
+/// the word, where the engine places it by the rules. Plain letters are
+/// kept as written, so foreign words that the method would transform
+/// (e.g., "w" in Telex) don't survive a round trip through `compose`.
+///
+/// None for other methods: VIQR modifiers are punctuation, which a word
+/// followed by '.' or '?' can't be told apart from.
+pub fn to_keystrokes(text: &str, method: u8) -> Option<String> {
+    if method > 1 {
+        return None;
+    }
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut word: Vec<(char, ParsedChar)> = Vec::new();
+
+    for ch in text.chars() {
+        match parse_char(ch).filter(|_| ch.is_alphabetic()) {
+            Some(p) => word.push((ch, p)),
+            None => {
+                word_keystrokes(&word, method == 1, &mut out);
+                word.clear();
+                out.push(ch);
+            }
+        }
+    }
+    word_keystrokes(&word, method == 1, &mut out);
+    Some(out)
+}
+
+/// Append the keystrokes of one word
+fn word_keystrokes(word: &[(char, ParsedChar)], vni: bool, out: &mut String) {
+    let mut tone_mark = mark::NONE;
+    for (i, &(ch, p)) in word.iter().enumerate() {
+        let Some(letter) = utils::key_to_char(p.key, p.caps) else {
+            out.push(ch);
+            continue;
+        };
+        out.push(letter);
+        let next = word.get(i + 1).map(|&(_, n)| n);
+
+        if p.stroke {
+            out.push(if vni { '9' } else { letter });
+        } else if p.tone == tone::CIRCUMFLEX {
+            out.push(if vni { '6' } else { letter });
+        } else if p.tone == tone::HORN {
+            // "ươ" takes one horn key after the o ("uow", "uo7")
+            let uo =
+                p.key == keys::U && next.is_some_and(|n| n.key == keys::O && n.tone == tone::HORN);
+            match (uo, vni) {
+                (true, _) => {}
+                (false, true) if p.key == keys::A => out.push('8'),
+                (false, true) => out.push('7'),
+                (false, false) => out.push(if p.caps { 'W' } else { 'w' }),
+            }
+        } else if !vni {
+            // A plain repeat would combine ("oo" → "ô"): type it a third time
+            let prev = i.checked_sub(1).map(|j| word[j].1);
+            let doubles = matches!(p.key, keys::A | keys::E | keys::O | keys::D);
+            if doubles && prev.is_some_and(|q| q.key == p.key && q.tone == tone::NONE && !q.stroke)
+            {
+                out.push(letter);
+            }
+        }
+        if p.mark != mark::NONE {
+            tone_mark = p.mark;
+        }
+    }
+
+    if tone_mark != mark::NONE {
+        let caps = word.last().is_some_and(|&(_, p)| p.caps);
+        let key = match (vni, tone_mark) {
+            (true, m) => (b'0' + m) as char,
+            (false, mark::SAC) => 's',
+            (false, mark::HUYEN) => 'f',
+            (false, mark::HOI) => 'r',
+            (false, mark::NGA) => 'x',
+            (false, _) => 'j',
+        };
+        out.push(if caps { key.to_ascii_uppercase() } else { key });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input;
+
+    const TEXT: &str = "Gõ Nhanh là bộ gõ tiếng Việt miễn phí, nhanh và nhẹ. \
+                        Người được giữa khuyết, quốc thuở xoong ĐẶNG Nguyễn! \
+                        Hoà thuỷ ươm rượu lưu cừu, chuyện gì? Oà. ĐƯỜNG";
+
+    fn config(method: u8) -> EngineConfig {
+        EngineConfig {
+            method,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compose() {
+        assert_eq!(
+            compose("Tooi yeeu tieengs Vieetj.\nDDuwowngf 1", &config(0)),
+            "Tôi yêu tiếng Việt.\nĐường 1"
+        );
+        assert_eq!(
+            compose("Xin chao2 ca1c ba5n", &config(1)),
+            "Xin chào các bạn"
+        );
+        // Composed text passes through
+        assert_eq!(compose("đã xong", &config(0)), "đã xong");
+    }
+
+    #[test]
+    fn test_to_keystrokes() {
+        let telex = |text| to_keystrokes(text, 0).unwrap();
+        assert_eq!(telex("Đường xoong"), "DDuowngf xooong");
+        assert_eq!(telex("ĂN Ở"), "AWN OWR");
+        assert_eq!(telex("3 ngày"), "3 ngayf");
+        assert_eq!(to_keystrokes("Đường xoong", 1).unwrap(), "D9uo7ng2 xoong");
+        assert_eq!(to_keystrokes("Đường", 2), None);
+        assert_eq!(to_keystrokes("Đường", input::TABLE), None);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for method in [0, 1] {
+            let keystrokes = to_keystrokes(TEXT, method).unwrap();
+            assert_eq!(
+                compose(&keystrokes, &config(method)),
+                TEXT,
+                "{}",
+                keystrokes
+            );
+        }
+    }
+}
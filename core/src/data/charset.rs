@@ -0,0 +1,329 @@
+//! Legacy output charsets
+//!
+//! Encodes composed Unicode characters into legacy Vietnamese encodings:
+//! - VNI-Windows: base letter + diacritic byte (some vowels are a single byte)
+//! - TCVN3 (ABC): one byte per character, uppercase marked vowels share the
+//!   lowercase code (rendered uppercase by the "H" fonts like .VnTimeH)
+//! - VIQR: 7-bit ASCII with punctuation diacritics (ế → e^')
+//!
+//! Plus Unicode NFD (base letter + combining marks) for hosts that expect
+//! decomposed text, like some Linux terminals and macOS file names.
+//!
+//! Legacy bytes are emitted as the Latin-1 codepoint of the same value,
+//! which is how documents using these fonts store them.
+
+use super::chars::{mark, parse_char, tone, ParsedChar};
+use super::keys;
+
+/// Output encoding for composed text
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Charset {
+    /// Precomposed Unicode (default)
+    #[default]
+    Unicode = 0,
+    VniWindows = 1,
+    Tcvn3 = 2,
+    Viqr = 3,
+    /// Unicode decomposed: base letter + combining marks (ế → e U+0302 U+0301)
+    Nfd = 4,
+}
+
+impl Charset {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Charset::Unicode),
+            1 => Some(Charset::VniWindows),
+            2 => Some(Charset::Tcvn3),
+            3 => Some(Charset::Viqr),
+            4 => Some(Charset::Nfd),
+            _ => None,
+        }
+    }
+}
+
+/// Encode one composed character, appending its code units to `out`
+pub fn encode_char(c: char, charset: Charset, out: &mut Vec<char>) {
+    let parsed = match parse_char(c) {
+        Some(p) if charset != Charset::Unicode && !c.is_ascii() => p,
+        _ => {
+            out.push(c);
+            return;
+        }
+    };
+    match charset {
+        Charset::Unicode => out.push(c),
+        Charset::VniWindows => encode_vni(parsed, out),
+        Charset::Tcvn3 => out.push(encode_tcvn3(parsed).map(char::from).unwrap_or(c)),
+        Charset::Viqr => encode_viqr(parsed, out),
+        Charset::Nfd => encode_nfd(parsed, out),
+    }
+}
+
+/// Encode a string
+pub fn encode(s: &str, charset: Charset) -> String {
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        encode_char(c, charset, &mut out);
+    }
+    out.into_iter().collect()
+}
+
+/// Number of code units `c` occupies once encoded (for backspace counts)
+pub fn encoded_len(c: char, charset: Charset) -> usize {
+    let mut out = Vec::with_capacity(3);
+    encode_char(c, charset, &mut out);
+    out.len()
+}
+
+/// Plain base letter for a parsed character
+fn base_char(p: ParsedChar) -> char {
+    crate::utils::key_to_char(p.key, p.caps).unwrap_or('?')
+}
+
+// ============================================================
+// VNI-Windows
+// ============================================================
+
+/// Mark bytes [sắc, huyền, hỏi, ngã, nặng] (lowercase, uppercase)
+const VNI_MARK: [[u8; 5]; 2] = [
+    [0xF9, 0xF8, 0xFB, 0xF5, 0xEF],
+    [0xD9, 0xD8, 0xDB, 0xD5, 0xCF],
+];
+
+/// Circumflex bytes [none, sắc, huyền, hỏi, ngã, nặng]
+const VNI_CIRCUMFLEX: [[u8; 6]; 2] = [
+    [0xE2, 0xE1, 0xE0, 0xE5, 0xE3, 0xE4],
+    [0xC2, 0xC1, 0xC0, 0xC5, 0xC3, 0xC4],
+];
+
+/// Breve bytes [none, sắc, huyền, hỏi, ngã, nặng]
+const VNI_BREVE: [[u8; 6]; 2] = [
+    [0xEA, 0xE9, 0xE8, 0xFA, 0xFC, 0xEB],
+    [0xCA, 0xC9, 0xC8, 0xDA, 0xDC, 0xCB],
+];
+
+/// Single-byte i with marks [sắc, huyền, hỏi, ngã, nặng]
+const VNI_I: [[u8; 5]; 2] = [
+    [0xED, 0xEC, 0xE6, 0xF3, 0xF2],
+    [0xCD, 0xCC, 0xC6, 0xD3, 0xD2],
+];
+
+fn encode_vni(p: ParsedChar, out: &mut Vec<char>) {
+    let case = p.caps as usize;
+    let push = |out: &mut Vec<char>, b: u8| out.push(char::from(b));
+
+    if p.stroke {
+        push(out, if p.caps { 0xD1 } else { 0xF1 });
+        return;
+    }
+
+    match (p.key, p.tone) {
+        // ơ, ư are single bytes; marks follow as a separate byte
+        (keys::O, tone::HORN) => push(out, if p.caps { 0xD4 } else { 0xF4 }),
+        (keys::U, tone::HORN) => push(out, if p.caps { 0xD6 } else { 0xF6 }),
+        (keys::I, _) if p.mark != mark::NONE => {
+            push(out, VNI_I[case][p.mark as usize - 1]);
+            return;
+        }
+        (keys::Y, _) if p.mark == mark::NANG => {
+            push(out, if p.caps { 0xCE } else { 0xEE });
+            return;
+        }
+        (_, tone::CIRCUMFLEX) => {
+            out.push(base_char(p));
+            push(out, VNI_CIRCUMFLEX[case][p.mark as usize]);
+            return;
+        }
+        (keys::A, tone::HORN) => {
+            out.push(base_char(p));
+            push(out, VNI_BREVE[case][p.mark as usize]);
+            return;
+        }
+        _ => out.push(base_char(p)),
+    }
+
+    if p.mark != mark::NONE {
+        push(out, VNI_MARK[case][p.mark as usize - 1]);
+    }
+}
+
+// ============================================================
+// TCVN3 (ABC)
+// ============================================================
+
+/// Vowel rows [none, sắc, huyền, hỏi, ngã, nặng] (lowercase codes)
+const TCVN3_A: [u8; 6] = [b'a', 0xB8, 0xB5, 0xB6, 0xB7, 0xB9];
+const TCVN3_A_BREVE: [u8; 6] = [0xA8, 0xBE, 0xBB, 0xBC, 0xBD, 0xC6];
+const TCVN3_A_CIRC: [u8; 6] = [0xA9, 0xCA, 0xC7, 0xC8, 0xC9, 0xCB];
+const TCVN3_E: [u8; 6] = [b'e', 0xD0, 0xCC, 0xCE, 0xCF, 0xD1];
+const TCVN3_E_CIRC: [u8; 6] = [0xAA, 0xD5, 0xD2, 0xD3, 0xD4, 0xD6];
+const TCVN3_I: [u8; 6] = [b'i', 0xDD, 0xD7, 0xD8, 0xDC, 0xDE];
+const TCVN3_O: [u8; 6] = [b'o', 0xE3, 0xDF, 0xE1, 0xE2, 0xE4];
+const TCVN3_O_CIRC: [u8; 6] = [0xAB, 0xE8, 0xE5, 0xE6, 0xE7, 0xE9];
+const TCVN3_O_HORN: [u8; 6] = [0xAC, 0xED, 0xEA, 0xEB, 0xEC, 0xEE];
+const TCVN3_U: [u8; 6] = [b'u', 0xF3, 0xEF, 0xF1, 0xF2, 0xF4];
+const TCVN3_U_HORN: [u8; 6] = [0xAD, 0xF8, 0xF5, 0xF6, 0xF7, 0xF9];
+const TCVN3_Y: [u8; 6] = [b'y', 0xFD, 0xFA, 0xFB, 0xFC, 0xFE];
+
+fn encode_tcvn3(p: ParsedChar) -> Option<u8> {
+    if p.stroke {
+        return Some(if p.caps { 0xA7 } else { 0xAE });
+    }
+
+    // Uppercase letters with a vowel modifier but no mark have their own codes
+    if p.caps && p.mark == mark::NONE {
+        return match (p.key, p.tone) {
+            (keys::A, tone::HORN) => Some(0xA1),
+            (keys::A, tone::CIRCUMFLEX) => Some(0xA2),
+            (keys::E, tone::CIRCUMFLEX) => Some(0xA3),
+            (keys::O, tone::CIRCUMFLEX) => Some(0xA4),
+            (keys::O, tone::HORN) => Some(0xA5),
+            (keys::U, tone::HORN) => Some(0xA6),
+            _ => None,
+        };
+    }
+
+    let row = match (p.key, p.tone) {
+        (keys::A, tone::NONE) => &TCVN3_A,
+        (keys::A, tone::HORN) => &TCVN3_A_BREVE,
+        (keys::A, tone::CIRCUMFLEX) => &TCVN3_A_CIRC,
+        (keys::E, tone::NONE) => &TCVN3_E,
+        (keys::E, tone::CIRCUMFLEX) => &TCVN3_E_CIRC,
+        (keys::I, _) => &TCVN3_I,
+        (keys::O, tone::NONE) => &TCVN3_O,
+        (keys::O, tone::CIRCUMFLEX) => &TCVN3_O_CIRC,
+        (keys::O, tone::HORN) => &TCVN3_O_HORN,
+        (keys::U, tone::NONE) => &TCVN3_U,
+        (keys::U, tone::HORN) => &TCVN3_U_HORN,
+        (keys::Y, _) => &TCVN3_Y,
+        _ => return None,
+    };
+    row.get(p.mark as usize).copied()
+}
+
+// ============================================================
+// VIQR
+// ============================================================
+
+fn encode_viqr(p: ParsedChar, out: &mut Vec<char>) {
+    out.push(base_char(p));
+    if p.stroke {
+        out.push(if p.caps { 'D' } else { 'd' });
+        return;
+    }
+    match (p.key, p.tone) {
+        (_, tone::CIRCUMFLEX) => out.push('^'),
+        (keys::A, tone::HORN) => out.push('('),
+        (_, tone::HORN) => out.push('+'),
+        _ => {}
+    }
+    match p.mark {
+        mark::SAC => out.push('\''),
+        mark::HUYEN => out.push('`'),
+        mark::HOI => out.push('?'),
+        mark::NGA => out.push('~'),
+        mark::NANG => out.push('.'),
+        _ => {}
+    }
+}
+
+// ============================================================
+// Unicode NFD
+// ============================================================
+
+/// Combining marks with their canonical combining class
+const COMBINING_CIRCUMFLEX: (char, u8) = ('\u{0302}', 230);
+const COMBINING_BREVE: (char, u8) = ('\u{0306}', 230);
+const COMBINING_HORN: (char, u8) = ('\u{031B}', 216);
+const COMBINING_MARKS: [(char, u8); 5] = [
+    ('\u{0301}', 230), // sắc
+    ('\u{0300}', 230), // huyền
+    ('\u{0309}', 230), // hỏi
+    ('\u{0303}', 230), // ngã
+    ('\u{0323}', 220), // nặng (dot below)
+];
+
+fn encode_nfd(p: ParsedChar, out: &mut Vec<char>) {
+    // đ has no decomposition
+    if p.stroke {
+        out.push(super::chars::get_d(p.caps));
+        return;
+    }
+    out.push(base_char(p));
+
+    let modifier = match (p.key, p.tone) {
+        (_, tone::CIRCUMFLEX) => Some(COMBINING_CIRCUMFLEX),
+        (keys::A, tone::HORN) => Some(COMBINING_BREVE),
+        (_, tone::HORN) => Some(COMBINING_HORN),
+        _ => None,
+    };
+    let mark = (p.mark != mark::NONE).then(|| COMBINING_MARKS[p.mark as usize - 1]);
+
+    // Canonical order: lower combining class first (ệ → e U+0323 U+0302)
+    let mut marks: Vec<(char, u8)> = modifier.into_iter().chain(mark).collect();
+    marks.sort_by_key(|&(_, ccc)| ccc);
+    out.extend(marks.into_iter().map(|(c, _)| c));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(s: &str, charset: Charset) -> Vec<u32> {
+        encode(s, charset).chars().map(|c| c as u32).collect()
+    }
+
+    #[test]
+    fn test_unicode_passthrough() {
+        assert_eq!(encode("Việt Nam", Charset::Unicode), "Việt Nam");
+    }
+
+    #[test]
+    fn test_viqr() {
+        assert_eq!(encode("Việt Nam", Charset::Viqr), "Vie^.t Nam");
+        assert_eq!(encode("đường", Charset::Viqr), "ddu+o+`ng");
+        assert_eq!(encode("Đắk", Charset::Viqr), "DDa('k");
+    }
+
+    #[test]
+    fn test_vni_windows() {
+        // á = a + ù, ấ = a + á, ắ = a + é
+        assert_eq!(bytes("á", Charset::VniWindows), [0x61, 0xF9]);
+        assert_eq!(bytes("ấ", Charset::VniWindows), [0x61, 0xE1]);
+        assert_eq!(bytes("ắ", Charset::VniWindows), [0x61, 0xE9]);
+        // ơ/ư single byte, marks appended
+        assert_eq!(bytes("ớ", Charset::VniWindows), [0xF4, 0xF9]);
+        assert_eq!(bytes("ư", Charset::VniWindows), [0xF6]);
+        // i with mark and ỵ are single bytes
+        assert_eq!(bytes("í", Charset::VniWindows), [0xED]);
+        assert_eq!(bytes("ỵ", Charset::VniWindows), [0xEE]);
+        assert_eq!(bytes("Đ", Charset::VniWindows), [0xD1]);
+        assert_eq!(bytes("Á", Charset::VniWindows), [0x41, 0xD9]);
+        assert_eq!(encoded_len('ệ', Charset::VniWindows), 2);
+    }
+
+    #[test]
+    fn test_nfd() {
+        let nfd = |s: &str| bytes(s, Charset::Nfd);
+        assert_eq!(nfd("ế"), [0x65, 0x302, 0x301]);
+        assert_eq!(nfd("ệ"), [0x65, 0x323, 0x302]);
+        assert_eq!(nfd("ợ"), [0x6F, 0x31B, 0x323]);
+        assert_eq!(nfd("Ẳ"), [0x41, 0x306, 0x309]);
+        assert_eq!(nfd("đ"), [0x111]);
+        assert_eq!(encode("Việt", Charset::Nfd).chars().count(), 6);
+    }
+
+    #[test]
+    fn test_tcvn3() {
+        assert_eq!(bytes("á", Charset::Tcvn3), [0xB8]);
+        assert_eq!(bytes("ệ", Charset::Tcvn3), [0xD6]);
+        assert_eq!(bytes("ự", Charset::Tcvn3), [0xF9]);
+        assert_eq!(bytes("đ", Charset::Tcvn3), [0xAE]);
+        assert_eq!(bytes("Đ", Charset::Tcvn3), [0xA7]);
+        assert_eq!(bytes("Ơ", Charset::Tcvn3), [0xA5]);
+        // Uppercase marked vowel shares the lowercase code
+        assert_eq!(bytes("Ấ", Charset::Tcvn3), [0xCA]);
+        assert_eq!(encoded_len('ệ', Charset::Tcvn3), 1);
+    }
+}
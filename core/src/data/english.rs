@@ -0,0 +1,60 @@
+//! Common English words for auto-restore
+//!
+//! `english_words.txt` (sorted, lowercase, one word per line) is embedded at
+//! compile time and binary-searched in place: no parsing at startup and no
+//! allocation per lookup. At ~1.9k words (~12 KB) a plain sorted list is
+//! small enough that an FST or compressed trie would not pay for a build step.
+//!
+//! The list vetoes heuristic restores of Vietnamese words in use whose keys
+//! are not in it ("sax" → "sã", "giso" → "gió"). It does not restore words
+//! the heuristics accept as Vietnamese: "mix" → "mĩ" and "cow" → "cơ" type
+//! the same keys as real words, so "mix" needs a user word
+//! (`Engine::add_english_word`).
+
+const WORDS: &str = include_str!("english_words.txt");
+
+/// Check if `word` (lowercase) is in the bundled list
+pub fn contains(word: &str) -> bool {
+    let (mut lo, mut hi) = (0, WORDS.len());
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        // Widen `mid` to the line containing it
+        let start = WORDS[..mid].rfind('\n').map_or(0, |i| i + 1);
+        let end = WORDS[mid..].find('\n').map_or(WORDS.len(), |i| mid + i);
+        match WORDS[start..end].cmp(word) {
+            std::cmp::Ordering::Equal => return true,
+            std::cmp::Ordering::Less => lo = end + 1,
+            std::cmp::Ordering::Greater => hi = start,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_sorted() {
+        let words: Vec<&str> = WORDS.lines().collect();
+        assert!(
+            words.windows(2).all(|w| w[0] < w[1]),
+            "sorted, no duplicates"
+        );
+        assert!(words
+            .iter()
+            .all(|w| !w.is_empty() && w.chars().all(|c| c.is_ascii_lowercase())));
+        assert!(words.iter().all(|w| contains(w)));
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(contains("text"));
+        assert!(contains("about"));
+        assert!(contains("zoom"));
+        assert!(!contains("tets"));
+        assert!(!contains(""));
+        assert!(!contains("Text"));
+        assert!(!contains("zzz"));
+    }
+}
@@ -65,6 +65,19 @@ pub const MINUS: u16 = 27;
 pub const EQUAL: u16 = 24;
 pub const BACKQUOTE: u16 = 50;
 
+/// Flag bit for Shift+key in method tables (Shift+6 '^' ≠ '6')
+pub const SHIFT: u16 = 0x100;
+
+/// Key code used for method lookup
+/// Letters ignore Shift (it only affects case); other keys carry the SHIFT bit
+pub fn with_shift(key: u16, shift: bool) -> u16 {
+    if shift && !is_letter(key) {
+        key | SHIFT
+    } else {
+        key
+    }
+}
+
 /// Check if key breaks word (space, punctuation, arrows, etc.)
 /// When shift=true, also treat number keys as break (they produce !@#$%^&*())
 pub fn is_break(key: u16) -> bool {
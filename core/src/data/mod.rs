@@ -3,11 +3,17 @@
 //! This module contains all linguistic data for Vietnamese input:
 //! - `keys`: Virtual keycode definitions (platform-specific)
 //! - `chars`: Unicode character conversion (includes tone/mark constants)
+//! - `charset`: Legacy output encodings (VNI-Windows, TCVN3, VIQR)
+//! - `english`: Common English words for auto-restore
+//! - `syllables`: Vietnamese syllables in use and their frequency
 //! - `vowel`: Vietnamese vowel phonology system
 
 pub mod chars;
+pub mod charset;
 pub mod constants;
+pub mod english;
 pub mod keys;
+pub mod syllables;
 pub mod vowel;
 
 pub use chars::{get_d, mark, to_char, tone};
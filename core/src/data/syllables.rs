@@ -0,0 +1,138 @@
+//! Vietnamese syllables in use
+//!
+//! `syllables.txt` lists the toneless syllables in use (~2.5k, sorted,
+//! lowercase, "nghiêng", "quyêt") and is binary-searched in place like the
+//! English list. It was collected by initial from words in use, rare ones
+//! included ("thuở", "bươu", "nguệch", "goòng"), not generated from spelling
+//! rules: "truych" spells fine but is no word. `test_common_words` checks it
+//! against text.
+//! Tones are not listed: any tone fits except that finals c, ch, p and t
+//! only take sắc or nặng, which gives ~11k toned forms.
+//!
+//! `syllable_freq.txt` ranks the ~400 most common toned syllables, most
+//! frequent first, in modern tone placement ("hoà"), for auto-accent.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const SYLLABLES: &str = include_str!("syllables.txt");
+const FREQUENT: &str = include_str!("syllable_freq.txt");
+
+/// Check if `base` (lowercase, no tone mark) is a syllable
+pub fn contains(base: &str) -> bool {
+    lower_bound(base) == Some(base)
+}
+
+/// Check if some syllable starts with `base` (lowercase, no tone mark)
+pub fn has_prefix(base: &str) -> bool {
+    lower_bound(base).is_some_and(|s| s.starts_with(base))
+}
+
+/// Frequency rank of a toned syllable (lowercase, modern placement), 0 = most common
+///
+/// None for syllables outside the list, which rank after all listed ones.
+pub fn rank(syllable: &str) -> Option<usize> {
+    static RANKS: OnceLock<HashMap<&str, usize>> = OnceLock::new();
+    let ranks = RANKS.get_or_init(|| FREQUENT.lines().enumerate().map(|(i, s)| (s, i)).collect());
+    ranks.get(syllable).copied()
+}
+
+/// First syllable not less than `base`
+fn lower_bound(base: &str) -> Option<&'static str> {
+    let (mut lo, mut hi) = (0, SYLLABLES.len());
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        // Widen `mid` to the line containing it; bytes, as `mid` may split a char
+        let bytes = SYLLABLES.as_bytes();
+        let start = bytes[..mid]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let end = bytes[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| start + i);
+        if &SYLLABLES[start..end] < base {
+            lo = end + 1;
+        } else {
+            hi = start;
+        }
+    }
+    SYLLABLES.get(lo..)?.lines().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_sorted() {
+        let syllables: Vec<&str> = SYLLABLES.lines().collect();
+        assert!(
+            syllables.windows(2).all(|w| w[0] < w[1]),
+            "sorted, no duplicates"
+        );
+        assert!(syllables.iter().all(|s| contains(s)));
+    }
+
+    #[test]
+    fn test_frequency_list() {
+        use crate::engine::validation::is_known_syllable;
+        use crate::spell::normalize;
+
+        let frequent: Vec<&str> = FREQUENT.lines().collect();
+        for (i, s) in frequent.iter().enumerate() {
+            assert!(is_known_syllable(s), "{}", s);
+            assert_eq!(&normalize(s, true), s, "modern placement");
+            assert_eq!(rank(s), Some(i), "duplicate {}", s);
+        }
+        assert_eq!(rank("của"), Some(0));
+        assert_eq!(rank("cuả"), None);
+    }
+
+    #[test]
+    fn test_lookup() {
+        for s in [
+            "a", "nghiêng", "ngươi", "quyêt", "đươc", "xương", "gi", "yêu",
+        ] {
+            assert!(contains(s), "{}", s);
+        }
+        assert!(!contains("quuyn"));
+        assert!(!contains("ngi"));
+        assert!(!contains(""));
+        assert!(has_prefix("nghiê"));
+        assert!(has_prefix(""));
+        assert!(!has_prefix("quu"));
+        assert!(!has_prefix("ngi"));
+    }
+
+    #[test]
+    fn test_common_words() {
+        use crate::engine::validation::is_known_syllable;
+
+        let text = "Thuở nhỏ tôi hay theo ông ra bờ sông xem người ta kéo goòng, \
+            tiếng coong coong vang xa. Con bươu vàng bò trên bờ ruộng, \
+            chân nguệch ngoạc mấy nét chữ. Nghĩa là khuya khoắt vẫn phải \
+            quấy bột, xếp quần áo, ngoáy tai, khuếch trương, huơ tay, \
+            loằng ngoằng, quỷ quyệt, chuyện trò, giường chiếu, khúc khuỷu, \
+            hoạ sĩ, xoong nồi, rượu, hươu, oẳn tù tì, giặt giũ, quýt, \
+            khoèo, ngoẹo, lưỡi, bướu, xoáy, tuần, gìn giữ, quốc, quên.";
+        for word in text
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|w| !w.is_empty())
+        {
+            assert!(is_known_syllable(word), "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_non_words() {
+        // Spelling allows these, but no word uses them
+        for s in [
+            "ruych", "thuych", "nguych", "troanh", "troao", "troeo", "truya", "truây", "truych",
+            "nhoăc", "pơ", "păn",
+        ] {
+            assert!(!contains(s), "{}", s);
+        }
+    }
+}
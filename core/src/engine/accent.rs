@@ -0,0 +1,347 @@
+//! Auto-accent suggestions
+//!
+//! With `auto_accent` on, a word typed without diacritics and committed
+//! with space gets toned candidates for the host to show: after
+//! "toi di hoc " the words offer "tôi", "đi" and "học" first.
+//! `accept_suggestion` swaps one in like any other rewrite, and `undo`
+//! swaps the typed word back.
+//!
+//! Candidates are the syllables in use whose letters fold to the typed
+//! word, ranked by:
+//! 1. How often they followed the previous word in history (learned from
+//!    words typed with diacritics and from accepted suggestions)
+//! 2. `syllables::rank`, then tone order
+
+use super::syllable;
+use super::undo::UndoEntry;
+use super::validation::is_known_syllable;
+use super::{encode_result, word_buffer, Action, Engine, Result};
+use crate::data::chars::{mark, parse_char, to_char};
+use crate::data::{keys, syllables};
+use crate::spell;
+use crate::utils;
+use std::collections::HashMap;
+
+/// Most candidates offered for a word
+pub const MAX_SUGGESTIONS: usize = 9;
+
+/// Word pairs remembered for context
+const BIGRAM_CAPACITY: usize = 4096;
+
+/// Tone marks in the order unranked candidates are offered
+const MARKS: [u8; 6] = [
+    mark::NONE,
+    mark::SAC,
+    mark::HUYEN,
+    mark::HOI,
+    mark::NGA,
+    mark::NANG,
+];
+
+/// (previous word, word) → times seen, as `pair_key` forms
+pub(super) type Bigrams = HashMap<(String, String), u32>;
+
+/// Candidates for the word just committed
+pub(super) struct Suggestions {
+    /// Word as typed
+    typed: String,
+    /// Word before it in history
+    previous: Option<String>,
+    candidates: Vec<String>,
+}
+
+/// Committed word captured before a space, for `accent_end`
+pub(super) struct AccentStart {
+    word: String,
+    previous: Option<String>,
+}
+
+impl Engine {
+    /// Candidates for the word just committed (empty once another key comes)
+    pub fn suggestions(&self) -> &[String] {
+        self.suggestions
+            .as_ref()
+            .map_or(&[], |s| s.candidates.as_slice())
+    }
+
+    /// Replace the word just committed with candidate `index`
+    ///
+    /// Returns the edit (the space is resent after the candidate), or
+    /// `Action::None` when there is no such candidate.
+    pub fn accept_suggestion(&mut self, index: usize) -> Result {
+        let result = self.accept(index);
+        self.record_accept(index, &result);
+        result
+    }
+
+    fn accept(&mut self, index: usize) -> Result {
+        let Some(candidate) = self.suggestions().get(index).cloned() else {
+            return Result::none();
+        };
+        let Some(s) = self.suggestions.take() else {
+            return Result::none();
+        };
+
+        // The next word's context is what ends up on screen
+        let chars: Vec<char> = candidate.chars().collect();
+        self.word_history.pop();
+        self.word_history.push(word_buffer(&chars));
+        if let Some(previous) = &s.previous {
+            self.learn(previous, &candidate);
+        }
+
+        let typed = format!("{} ", s.typed);
+        let shown = format!("{} ", candidate);
+        self.undo.clear();
+        self.undo_keys.clear();
+        self.undo.push(UndoEntry::Boundary {
+            shown: shown.clone(),
+            typed: typed.clone(),
+        });
+        let shown: Vec<char> = shown.chars().collect();
+        let result = Result::send(typed.chars().count() as u8, &shown);
+        encode_result(result, &typed, self.output_charset)
+    }
+
+    /// Drop the suggestions and capture the word a space is about to commit
+    pub(super) fn accent_begin(&mut self, key: u16, ctrl: bool) -> Option<AccentStart> {
+        self.suggestions = None;
+        if !self.auto_accent || key != keys::SPACE || ctrl || self.buf.is_empty() {
+            return None;
+        }
+        Some(AccentStart {
+            word: self.buf.to_full_string(),
+            previous: self.word_history.iter().last().map(|b| b.to_full_string()),
+        })
+    }
+
+    /// Learn from or suggest for the committed word
+    pub(super) fn accent_end(&mut self, start: Option<AccentStart>, result: &Result) {
+        // A shortcut or restore rewrote the word
+        let Some(AccentStart { word, previous }) =
+            start.filter(|_| result.action == Action::None as u8)
+        else {
+            return;
+        };
+        if !word.is_ascii() {
+            if let Some(previous) = &previous {
+                if is_known_syllable(&word) {
+                    self.learn(previous, &word);
+                }
+            }
+            return;
+        }
+
+        let mut candidates = candidates(&word, self.modern_tone);
+        let previous_key = previous.as_deref().map(pair_key);
+        // Stable: unranked candidates keep tone order
+        candidates.sort_by_cached_key(|c| {
+            let key = pair_key(c);
+            let seen = previous_key.as_ref().map_or(0, |p| {
+                self.bigrams
+                    .get(&(p.clone(), key.clone()))
+                    .copied()
+                    .unwrap_or(0)
+            });
+            let rank = syllables::rank(&key).unwrap_or(usize::MAX);
+            (std::cmp::Reverse(seen), rank)
+        });
+        candidates.truncate(MAX_SUGGESTIONS);
+        if !candidates.is_empty() {
+            self.suggestions = Some(Suggestions {
+                typed: word,
+                previous,
+                candidates,
+            });
+        }
+    }
+
+    /// Count `word` following `previous`
+    fn learn(&mut self, previous: &str, word: &str) {
+        if self.bigrams.len() >= BIGRAM_CAPACITY {
+            // Forget pairs seen once, or start over
+            self.bigrams.retain(|_, n| *n > 1);
+            if self.bigrams.len() >= BIGRAM_CAPACITY {
+                self.bigrams.clear();
+            }
+        }
+        *self
+            .bigrams
+            .entry((pair_key(previous), pair_key(word)))
+            .or_default() += 1;
+    }
+}
+
+/// Lowercase, modern placement: one form per syllable for counting and ranking
+fn pair_key(word: &str) -> String {
+    spell::normalize(&word.to_lowercase(), true)
+}
+
+/// Toned syllables in use whose letters are `word` (ASCII), other than
+/// `word` itself, in its case
+fn candidates(word: &str, modern_tone: bool) -> Vec<String> {
+    let lower = word.to_ascii_lowercase();
+    let Some(word_keys) = lower
+        .chars()
+        .map(|c| utils::char_to_key_ext(c).map(|(key, _, _)| key))
+        .collect::<Option<Vec<u16>>>()
+    else {
+        return vec![];
+    };
+    let syllable = syllable::parse(&word_keys);
+    if syllable.is_empty() || !lower.chars().all(|c| c.is_ascii_alphabetic()) {
+        return vec![];
+    }
+
+    // Letters that can take a diacritic: initial d and the vowels
+    let mut choices: Vec<&str> = vec![""; word_keys.len()];
+    if word_keys[0] == keys::D && syllable.initial.contains(&0) {
+        choices[0] = "dđ";
+    }
+    for &i in syllable.glide.iter().chain(&syllable.vowel) {
+        choices[i] = match word_keys[i] {
+            keys::A => "aâă",
+            keys::E => "eê",
+            keys::O => "oôơ",
+            keys::U => "uư",
+            _ => "",
+        };
+    }
+    let mut bases = vec![String::new()];
+    for (c, choice) in lower.chars().zip(&choices) {
+        let options: Vec<char> = match *choice {
+            "" => vec![c],
+            choice => choice.chars().collect(),
+        };
+        bases = bases
+            .iter()
+            .flat_map(|b| options.iter().map(move |&o| format!("{}{}", b, o)))
+            .collect();
+    }
+
+    let nucleus = syllable.vowel[0];
+    let mut out = vec![];
+    for base in bases.iter().filter(|b| syllables::contains(b)) {
+        // Stop finals are never toneless in words ("hôc" is no word, "học" is)
+        let stop = base.ends_with(['c', 'p', 't']) || base.ends_with("ch");
+        for tone_mark in MARKS.into_iter().skip(stop as usize) {
+            // Mark the nucleus, then let the placement rules move it
+            let marked: String = base
+                .chars()
+                .enumerate()
+                .map(|(i, c)| match parse_char(c) {
+                    Some(p) if i == nucleus => {
+                        to_char(p.key, false, p.tone, tone_mark).unwrap_or(c)
+                    }
+                    _ => c,
+                })
+                .collect();
+            let toned = spell::normalize(&marked, modern_tone);
+            if toned != lower && is_known_syllable(&toned) {
+                let cased = toned.chars().zip(word.chars()).map(|(c, typed)| {
+                    if typed.is_uppercase() {
+                        c.to_uppercase().next().unwrap_or(c)
+                    } else {
+                        c
+                    }
+                });
+                out.push(cased.collect());
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::type_word;
+
+    fn accent_engine() -> Engine {
+        let mut e = Engine::new();
+        e.set_auto_accent(true);
+        e
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(candidates("toi", true)[..3], ["tói", "tòi", "tỏi"]);
+        assert!(candidates("di", true).contains(&"đi".to_string()));
+        assert!(candidates("hoa", true).contains(&"hoà".to_string()));
+        assert!(candidates("hoa", false).contains(&"hòa".to_string()));
+        // Stop finals take sắc or nặng only
+        assert_eq!(candidates("hoc", true), ["hóc", "học", "hốc", "hộc"]);
+        assert_eq!(candidates("Viet", true), ["Viết", "Việt"]);
+        assert!(candidates("xyz", true).is_empty());
+    }
+
+    #[test]
+    fn test_suggest_and_accept() {
+        let mut e = accent_engine();
+        let mut screen = type_word(&mut e, "toi ");
+        assert_eq!(e.suggestions()[0], "tôi");
+        assert_eq!(e.suggestions().len(), MAX_SUGGESTIONS);
+
+        let r = e.accept_suggestion(0);
+        assert_eq!(r.backspace, 4);
+        for _ in 0..r.backspace {
+            screen.pop();
+        }
+        screen.extend(
+            r.chars[..r.count as usize]
+                .iter()
+                .filter_map(|&c| char::from_u32(c)),
+        );
+        assert_eq!(screen, "tôi ");
+        assert!(e.suggestions().is_empty());
+        assert_eq!(e.accept_suggestion(0).action, Action::None as u8);
+
+        screen.push_str(&type_word(&mut e, "di "));
+        assert_eq!(e.suggestions()[0], "đi");
+        // The next key drops them
+        type_word(&mut e, "h");
+        assert!(e.suggestions().is_empty());
+        type_word(&mut e, "oc ");
+        assert_eq!(e.suggestions()[0], "học");
+
+        // Off by default; plain words only
+        let mut e = Engine::new();
+        type_word(&mut e, "toi ");
+        assert!(e.suggestions().is_empty());
+        let mut e = accent_engine();
+        type_word(&mut e, "tooi ");
+        assert!(e.suggestions().is_empty());
+    }
+
+    #[test]
+    fn test_context() {
+        let mut e = accent_engine();
+        // "bàn" wins over the more common "bạn" after "cái"
+        type_word(&mut e, "ban ");
+        assert_eq!(e.suggestions()[0], "bạn");
+        type_word(&mut e, "cais banf cais banf cais ban ");
+        assert_eq!(e.suggestions()[0], "bàn");
+
+        // Accepting teaches too
+        let mut e = accent_engine();
+        type_word(&mut e, "ngon ngu ");
+        let ngu = e.suggestions().iter().position(|c| c == "ngữ").unwrap();
+        e.accept_suggestion(ngu);
+        type_word(&mut e, "ngon ngu ");
+        assert_eq!(e.suggestions()[0], "ngữ");
+    }
+
+    #[test]
+    fn test_undo_accept() {
+        let mut e = accent_engine();
+        type_word(&mut e, "Hoc ");
+        assert_eq!(e.suggestions()[0], "Học");
+        e.accept_suggestion(0);
+        let r = e.undo();
+        let text: String = r.chars[..r.count as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect();
+        assert_eq!((r.backspace, text.as_str()), (3, "oc "));
+    }
+}
@@ -112,6 +112,17 @@ impl Buffer {
         }
     }
 
+    /// Insert element at index, shifting subsequent elements right
+    pub fn insert(&mut self, index: usize, c: Char) {
+        if index <= self.len && self.len < MAX {
+            for i in (index..self.len).rev() {
+                self.data[i + 1] = self.data[i];
+            }
+            self.data[index] = c;
+            self.len += 1;
+        }
+    }
+
     /// Remove element at index, shifting subsequent elements left
     pub fn remove(&mut self, index: usize) {
         if index < self.len {
@@ -182,6 +193,14 @@ impl Buffer {
             })
             .collect()
     }
+
+    /// Convert buffer to decomposed (NFD) string: base letters + combining marks
+    ///
+    /// Matches what NFD output mode puts on screen, for "Select All + Replace" hosts.
+    pub fn to_full_string_nfd(&self) -> String {
+        use crate::data::charset::{self, Charset};
+        charset::encode(&self.to_full_string(), Charset::Nfd)
+    }
 }
 
 #[cfg(test)]
@@ -204,4 +223,33 @@ mod tests {
         buf.clear();
         assert!(buf.is_empty());
     }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut buf = Buffer::new();
+        buf.push(Char::new(0, false));
+        buf.push(Char::new(2, false));
+        buf.insert(1, Char::new(1, false));
+        buf.insert(3, Char::new(3, false));
+        assert_eq!(buf.iter().map(|c| c.key).collect::<Vec<_>>(), [0, 1, 2, 3]);
+
+        buf.remove(0);
+        assert_eq!(buf.iter().map(|c| c.key).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_to_full_string_nfd() {
+        use crate::data::{chars, keys};
+        let mut buf = Buffer::new();
+        buf.push(Char::new(keys::V, true));
+        buf.push(Char::new(keys::I, false));
+        let mut e = Char::new(keys::E, false);
+        e.tone = chars::tone::CIRCUMFLEX;
+        e.mark = chars::mark::NANG;
+        buf.push(e);
+        buf.push(Char::new(keys::T, false));
+
+        assert_eq!(buf.to_full_string(), "Việt");
+        assert_eq!(buf.to_full_string_nfd(), "Vie\u{0323}\u{0302}t");
+    }
 }
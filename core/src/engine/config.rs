@@ -0,0 +1,272 @@
+//! Engine configuration
+//!
+//! All user options in one struct with a text format shared by every host,
+//! instead of one FFI setter per option persisted differently per platform.
+//!
+//! ```text
+//! # Gõ Nhanh settings
+//! version = 1
+//! method = "telex"            # telex | vni | viqr | table
+//! enabled = true
+//! skip_w_shortcut = false
+//! esc_restore = false
+//! free_tone = false
+//! modern_tone = true
+//! english_auto_restore = false
+//! english_dictionary = false
+//! strict_syllables = false
+//! auto_accent = false
+//! auto_capitalize = false
+//! caret_tracking = false
+//! output_charset = "unicode"  # unicode | vni-windows | tcvn3 | viqr | nfd
+//! ```
+//!
+//! - Missing keys keep their defaults, so older files stay loadable
+//! - Unknown keys and newer versions are errors rather than silently dropped
+
+use super::Engine;
+use crate::data::charset::Charset;
+use crate::input;
+use crate::lines::{self, LineError};
+use std::fmt;
+
+/// Current config format version
+pub const VERSION: u32 = 1;
+
+/// User options of an engine (see `Engine::config`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EngineConfig {
+    /// 0=Telex, 1=VNI, 2=VIQR, `input::TABLE`=loaded table
+    pub method: u8,
+    pub enabled: bool,
+    pub skip_w_shortcut: bool,
+    pub esc_restore: bool,
+    pub free_tone: bool,
+    pub modern_tone: bool,
+    pub english_auto_restore: bool,
+    pub english_dictionary: bool,
+    pub strict_syllables: bool,
+    pub auto_accent: bool,
+    pub auto_capitalize: bool,
+    pub caret_tracking: bool,
+    pub output_charset: Charset,
+}
+
+impl Default for EngineConfig {
+    /// Same options as `Engine::new`
+    fn default() -> Self {
+        Self {
+            method: 0,
+            enabled: true,
+            skip_w_shortcut: false,
+            esc_restore: false,
+            free_tone: false,
+            modern_tone: true,
+            english_auto_restore: false,
+            english_dictionary: false,
+            strict_syllables: false,
+            auto_accent: false,
+            auto_capitalize: false,
+            caret_tracking: false,
+            output_charset: Charset::Unicode,
+        }
+    }
+}
+
+impl EngineConfig {
+    /// Parse a config file (see module docs for the format)
+    pub fn parse(text: &str) -> Result<Self, LineError> {
+        let mut config = Self::default();
+
+        for (line, content) in lines::content_lines(text) {
+            let err = |reason| LineError { line, reason };
+            let (key, value) = lines::key_value(content).map_err(err)?;
+            if key == "version" {
+                lines::check_version(value, VERSION).map_err(err)?;
+                continue;
+            }
+            config.set(key, value).map_err(err)?;
+        }
+        Ok(config)
+    }
+
+    /// Set one option by its file key (e.g., `set("method", "vni")`)
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), &'static str> {
+        let flag = || match value {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            _ => Err("expected true or false"),
+        };
+        match key {
+            "method" => self.method = method_id(value).ok_or("unknown method")?,
+            "enabled" => self.enabled = flag()?,
+            "skip_w_shortcut" => self.skip_w_shortcut = flag()?,
+            "esc_restore" => self.esc_restore = flag()?,
+            "free_tone" => self.free_tone = flag()?,
+            "modern_tone" => self.modern_tone = flag()?,
+            "english_auto_restore" => self.english_auto_restore = flag()?,
+            "english_dictionary" => self.english_dictionary = flag()?,
+            "strict_syllables" => self.strict_syllables = flag()?,
+            "auto_accent" => self.auto_accent = flag()?,
+            "auto_capitalize" => self.auto_capitalize = flag()?,
+            "caret_tracking" => self.caret_tracking = flag()?,
+            "output_charset" => self.output_charset = charset_id(value).ok_or("unknown charset")?,
+            _ => return Err("unknown key"),
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for EngineConfig {
+    /// Serialize in the format read by `parse`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version = {}", VERSION)?;
+        writeln!(f, "method = \"{}\"", method_name(self.method))?;
+        writeln!(f, "enabled = {}", self.enabled)?;
+        writeln!(f, "skip_w_shortcut = {}", self.skip_w_shortcut)?;
+        writeln!(f, "esc_restore = {}", self.esc_restore)?;
+        writeln!(f, "free_tone = {}", self.free_tone)?;
+        writeln!(f, "modern_tone = {}", self.modern_tone)?;
+        writeln!(f, "english_auto_restore = {}", self.english_auto_restore)?;
+        writeln!(f, "english_dictionary = {}", self.english_dictionary)?;
+        writeln!(f, "strict_syllables = {}", self.strict_syllables)?;
+        writeln!(f, "auto_accent = {}", self.auto_accent)?;
+        writeln!(f, "auto_capitalize = {}", self.auto_capitalize)?;
+        writeln!(f, "caret_tracking = {}", self.caret_tracking)?;
+        writeln!(
+            f,
+            "output_charset = \"{}\"",
+            charset_name(self.output_charset)
+        )
+    }
+}
+
+impl Engine {
+    /// Current user options
+    pub fn config(&self) -> EngineConfig {
+        EngineConfig {
+            method: self.method,
+            enabled: self.enabled,
+            skip_w_shortcut: self.skip_w_shortcut,
+            esc_restore: self.esc_restore_enabled,
+            free_tone: self.free_tone_enabled,
+            modern_tone: self.modern_tone,
+            english_auto_restore: self.english_auto_restore,
+            english_dictionary: self.english_dictionary,
+            strict_syllables: self.strict_syllables,
+            auto_accent: self.auto_accent,
+            auto_capitalize: self.auto_capitalize,
+            caret_tracking: self.caret_tracking,
+            output_charset: self.output_charset,
+        }
+    }
+
+    /// Apply all options at once (same effect as each setter)
+    pub fn apply_config(&mut self, config: &EngineConfig) {
+        self.set_method(config.method);
+        self.set_enabled(config.enabled);
+        self.set_skip_w_shortcut(config.skip_w_shortcut);
+        self.set_esc_restore(config.esc_restore);
+        self.set_free_tone(config.free_tone);
+        self.set_modern_tone(config.modern_tone);
+        self.set_english_auto_restore(config.english_auto_restore);
+        self.set_english_dictionary(config.english_dictionary);
+        self.set_strict_syllables(config.strict_syllables);
+        self.set_auto_accent(config.auto_accent);
+        self.set_auto_capitalize(config.auto_capitalize);
+        self.set_caret_tracking(config.caret_tracking);
+        self.set_output_charset(config.output_charset);
+    }
+}
+
+fn method_id(name: &str) -> Option<u8> {
+    match name {
+        "telex" => Some(0),
+        "vni" => Some(1),
+        "viqr" => Some(2),
+        "table" => Some(input::TABLE),
+        _ => None,
+    }
+}
+
+/// Unknown ids behave like Telex (see `input::get`)
+fn method_name(id: u8) -> &'static str {
+    match id {
+        1 => "vni",
+        2 => "viqr",
+        input::TABLE => "table",
+        _ => "telex",
+    }
+}
+
+fn charset_id(name: &str) -> Option<Charset> {
+    match name {
+        "unicode" => Some(Charset::Unicode),
+        "vni-windows" => Some(Charset::VniWindows),
+        "tcvn3" => Some(Charset::Tcvn3),
+        "viqr" => Some(Charset::Viqr),
+        "nfd" => Some(Charset::Nfd),
+        _ => None,
+    }
+}
+
+fn charset_name(charset: Charset) -> &'static str {
+    match charset {
+        Charset::Unicode => "unicode",
+        Charset::VniWindows => "vni-windows",
+        Charset::Tcvn3 => "tcvn3",
+        Charset::Viqr => "viqr",
+        Charset::Nfd => "nfd",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let config = EngineConfig {
+            method: 1,
+            free_tone: true,
+            modern_tone: false,
+            english_dictionary: true,
+            strict_syllables: true,
+            auto_accent: true,
+            caret_tracking: true,
+            output_charset: Charset::Tcvn3,
+            ..Default::default()
+        };
+        let text = config.to_string();
+        assert!(text.starts_with("version = 1\n"));
+        assert_eq!(EngineConfig::parse(&text), Ok(config));
+
+        let mut e = Engine::new();
+        e.apply_config(&config);
+        assert_eq!(e.config(), config);
+        assert_eq!(Engine::new().config(), EngineConfig::default());
+    }
+
+    #[test]
+    fn test_partial_and_comments() {
+        let config = EngineConfig::parse(
+            "# shared settings\nmethod = \"viqr\" # for old docs\n\nauto_capitalize=true\n",
+        )
+        .unwrap();
+        assert_eq!(config.method, 2);
+        assert!(config.auto_capitalize);
+        assert!(config.modern_tone);
+    }
+
+    #[test]
+    fn test_errors() {
+        let line = |text: &str| EngineConfig::parse(text).unwrap_err();
+        assert_eq!(line("version = 1\ncolor = red").line, 2);
+        assert_eq!(line("version = 1\ncolor = red").reason, "unknown key");
+        assert_eq!(line("version = 2").reason, "unsupported version");
+        assert_eq!(line("method = \"dvorak\"").reason, "unknown method");
+        assert_eq!(line("\n\nfree_tone = yes").line, 3);
+        assert_eq!(line("modern_tone").reason, "expected key = value");
+        assert_eq!(line("output_charset = latin1").reason, "unknown charset");
+    }
+}
@@ -10,22 +10,40 @@
 //! 3. **Shortcut Support**: User-defined abbreviations with priority
 //! 4. **Longest-Match-First**: For diacritic placement
 
+pub mod accent;
 pub mod buffer;
+pub mod config;
+pub mod policy;
+pub mod record;
 pub mod shortcut;
+pub mod state;
 pub mod syllable;
+pub mod template;
 pub mod transform;
+pub mod undo;
 pub mod validation;
 
 use crate::data::{
     chars::{self, mark, tone},
-    constants, keys,
+    charset::{self, Charset},
+    constants, english, keys,
     vowel::{Phonology, Vowel},
 };
-use crate::input::{self, ToneType};
+use crate::input::{self, MethodRef, TableMethod, ToneType};
 use crate::utils;
+use accent::{Bigrams, Suggestions};
 use buffer::{Buffer, Char, MAX};
-use shortcut::{InputMethod, ShortcutTable};
-use validation::{is_foreign_word_pattern, is_valid, is_valid_for_transform, is_valid_with_tones};
+use config::EngineConfig;
+use policy::AppPolicy;
+use record::Recorder;
+use shortcut::{InputMethod, InputPurpose, ShortcutMatch, ShortcutTable};
+use state::WordState;
+use std::sync::Arc;
+use undo::UndoEntry;
+use validation::{
+    is_foreign_word_pattern, is_known_syllable, is_known_syllable_prefix, is_valid,
+    is_valid_for_transform, is_valid_with_tones,
+};
 
 /// Engine action result
 #[repr(u8)]
@@ -52,6 +70,9 @@ pub struct Result {
 /// Flag: key was consumed by shortcut, don't pass through
 pub const FLAG_KEY_CONSUMED: u8 = 0x01;
 
+/// Modifier bit for `on_char`: Cmd/Ctrl/Alt is pressed (bypasses IME)
+pub const MOD_CTRL: u8 = 0x01;
+
 impl Result {
     pub fn none() -> Self {
         Self {
@@ -90,6 +111,41 @@ impl Result {
     }
 }
 
+/// Variable-length edit (Rust side of `ime_key_v2`)
+///
+/// Unlike `Result`, `text` is not capped at MAX codepoints.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Edit {
+    /// Same values as `Result.action`
+    pub action: u8,
+    /// Characters to delete before inserting
+    pub backspace: usize,
+    /// Characters to delete after the caret (edits inside a word)
+    pub delete_after: usize,
+    /// Text to insert
+    pub text: String,
+    /// Caret position inside `text` in chars (None = after the text)
+    pub cursor: Option<usize>,
+    /// Trigger key was consumed, don't pass it through
+    pub key_consumed: bool,
+}
+
+impl From<&Result> for Edit {
+    /// The edit a fixed-size result makes (no caret edit)
+    fn from(result: &Result) -> Self {
+        Edit {
+            action: result.action,
+            backspace: result.backspace as usize,
+            text: result.chars[..result.count as usize]
+                .iter()
+                .filter_map(|&c| char::from_u32(c))
+                .collect(),
+            key_consumed: result.key_consumed(),
+            ..Default::default()
+        }
+    }
+}
+
 /// Transform type for revert tracking
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum Transform {
@@ -151,6 +207,13 @@ impl WordHistory {
         self.len = 0;
         self.head = 0;
     }
+
+    /// Buffers from oldest to most recent
+    fn iter(&self) -> impl Iterator<Item = &Buffer> {
+        (0..self.len).map(move |i| {
+            &self.data[(self.head + HISTORY_CAPACITY - self.len + i) % HISTORY_CAPACITY]
+        })
+    }
 }
 
 /// Check if key is sentence-ending punctuation (triggers auto-capitalize)
@@ -240,10 +303,77 @@ fn break_key_to_char(key: u16, shift: bool) -> Option<char> {
     }
 }
 
+/// Re-encode a Unicode result for a legacy output charset
+///
+/// `before` is the word on screen before the key: each deleted char costs
+/// its encoded length in backspaces. Chars beyond it (spaces, shortcut
+/// prefixes) are ASCII and cost one.
+fn encode_result(r: Result, before: &str, output: Charset) -> Result {
+    if r.action != Action::Send as u8 {
+        return r;
+    }
+    let mut deleted = before.chars().rev();
+    let backspace: usize = (0..r.backspace)
+        .map(|_| {
+            deleted
+                .next()
+                .map_or(1, |c| charset::encoded_len(c, output))
+        })
+        .sum();
+
+    let mut chars = Vec::with_capacity(r.count as usize * 2);
+    for &cp in &r.chars[..r.count as usize] {
+        if let Some(c) = char::from_u32(cp) {
+            charset::encode_char(c, output, &mut chars);
+        }
+    }
+
+    let mut encoded = Result::send(backspace.min(u8::MAX as usize) as u8, &chars);
+    encoded.flags = r.flags;
+    encoded
+}
+
+/// Buffer for a word of Vietnamese text (chars must be `parse_char`-able)
+fn word_buffer(word: &[char]) -> Buffer {
+    let mut buf = Buffer::new();
+    for parsed in word.iter().filter_map(|&c| chars::parse_char(c)) {
+        let mut ch = Char::new(parsed.key, parsed.caps);
+        ch.tone = parsed.tone;
+        ch.mark = parsed.mark;
+        ch.stroke = parsed.stroke;
+        buf.push(ch);
+    }
+    buf
+}
+
 /// Main Vietnamese IME engine
 pub struct Engine {
     buf: Buffer,
     method: u8,
+    /// User-defined method table, active when `method == input::TABLE`
+    table: Option<Arc<TableMethod>>,
+    /// Encoding of `Result.chars` (Unicode unless a legacy charset is selected)
+    output_charset: Charset,
+    /// Full output of the current key when it doesn't fit in `Result` (long expansions)
+    long_output: Option<String>,
+    /// Left arrow steps into the word instead of ending it (host tracks the caret)
+    caret_tracking: bool,
+    /// Caret position inside the word, moved with Left/Right (None = at the end)
+    caret: Option<usize>,
+    /// Edit of the current key reaching past the caret: (delete_after, cursor in text)
+    caret_edit: Option<(usize, usize)>,
+    /// Caller applies `Edit.delete_after` (set during `on_key_edit`)
+    can_delete_after: bool,
+    /// Per-app option overrides, applied on focus change
+    policy: AppPolicy,
+    /// Global options kept aside while an app's overrides are active
+    policy_base: Option<EngineConfig>,
+    /// Undoable steps, latest last (see `undo`)
+    undo: Vec<UndoEntry>,
+    /// Keys of the current word since it started, for `undo`: (key, caps, shift)
+    undo_keys: Vec<(u16, bool, bool)>,
+    /// Key log being written (see `start_recording`)
+    recorder: Option<Recorder>,
     enabled: bool,
     last_transform: Option<Transform>,
     shortcuts: ShortcutTable,
@@ -269,6 +399,18 @@ pub struct Engine {
     /// When true, automatically restores English words that were transformed
     /// e.g., "tẽt" → "text", "ễpct" → "expect"
     english_auto_restore: bool,
+    /// Keep valid Vietnamese unless the raw word is in the English word list
+    english_dictionary: bool,
+    /// User-added English words, lowercase and sorted (always auto-restored)
+    english_words: Vec<String>,
+    /// Only transform into syllables in use (see `validation::is_known_syllable`)
+    strict_syllables: bool,
+    /// Offer toned candidates for words committed without diacritics
+    auto_accent: bool,
+    /// Candidates for the word just committed (see `suggestions`)
+    suggestions: Option<Suggestions>,
+    /// Word pairs seen, for ranking candidates
+    bigrams: Bigrams,
     /// Word history for backspace-after-space feature
     word_history: WordHistory,
     /// Number of spaces typed after committing a word (for backspace tracking)
@@ -332,6 +474,18 @@ impl Engine {
         Self {
             buf: Buffer::new(),
             method: 0,
+            table: None,
+            output_charset: Charset::Unicode,
+            long_output: None,
+            caret_tracking: false,
+            caret: None,
+            caret_edit: None,
+            can_delete_after: false,
+            policy: AppPolicy::new(),
+            policy_base: None,
+            undo: vec![],
+            undo_keys: vec![],
+            recorder: None,
             enabled: true,
             last_transform: None,
             shortcuts: ShortcutTable::with_defaults(),
@@ -342,6 +496,12 @@ impl Engine {
             free_tone_enabled: false,
             modern_tone: true,           // Default: modern style (hoà, thuý)
             english_auto_restore: false, // Default: OFF (experimental feature)
+            english_dictionary: false,
+            english_words: vec![],
+            strict_syllables: false,
+            auto_accent: false,
+            suggestions: None,
+            bigrams: Bigrams::new(),
             word_history: WordHistory::new(),
             spaces_after_commit: 0,
             pending_breve_pos: None,
@@ -363,6 +523,38 @@ impl Engine {
         self.method = method;
     }
 
+    /// Install a user-defined method table and switch to it
+    /// The table stays loaded, so `set_method(input::TABLE)` re-activates it.
+    pub fn load_method(&mut self, table: TableMethod) {
+        self.table = Some(Arc::new(table));
+        self.method = input::TABLE;
+        self.reset();
+    }
+
+    /// Set the encoding of output characters (legacy charsets for old documents)
+    pub fn set_output_charset(&mut self, charset: Charset) {
+        self.output_charset = charset;
+    }
+
+    pub fn output_charset(&self) -> Charset {
+        self.output_charset
+    }
+
+    /// Key mapping of the active method (falls back to Telex if no table loaded)
+    fn method_ref(&self) -> MethodRef {
+        match (&self.table, self.method) {
+            (Some(table), input::TABLE) => MethodRef::Table(Arc::clone(table)),
+            _ => MethodRef::Builtin(input::get(self.method)),
+        }
+    }
+
+    /// Digit the current method uses as a modifier (VNI, digit tables)
+    fn is_modifier_digit(&self, key: u16) -> bool {
+        let m = self.method_ref();
+        keys::is_number(key)
+            && (m.mark(key).is_some() || m.tone(key).is_some() || m.stroke(key) || m.remove(key))
+    }
+
     pub fn set_enabled(&mut self, enabled: bool) {
         self.enabled = enabled;
         if !enabled {
@@ -372,6 +564,19 @@ impl Engine {
         }
     }
 
+    /// Set the focused app and field type
+    ///
+    /// Options switch to the app's policy (see `policy`).
+    /// Scoped shortcuts only expand in matching contexts (see `Shortcut::applies_in`).
+    /// Terminal fields get no Vietnamese transforms (like `set_enabled(false)`),
+    /// password fields get neither transforms nor shortcuts.
+    pub fn set_context(&mut self, app_id: &str, purpose: InputPurpose) {
+        self.reset_all();
+        self.shortcut_prefix.clear();
+        self.shortcuts.set_context(app_id, purpose);
+        self.apply_policy(app_id);
+    }
+
     /// Set whether to skip w→ư shortcut in Telex mode
     pub fn set_skip_w_shortcut(&mut self, skip: bool) {
         self.skip_w_shortcut = skip;
@@ -397,6 +602,67 @@ impl Engine {
         self.english_auto_restore = enabled;
     }
 
+    /// Set whether auto-restore checks the bundled English word list
+    ///
+    /// Fewer false restores: Vietnamese words in use ("giso" → "gió") are
+    /// only restored when their keys spell common English. Words the
+    /// heuristics keep ("mix" → "mĩ") are not affected; see `data::english`.
+    pub fn set_english_dictionary(&mut self, enabled: bool) {
+        self.english_dictionary = enabled;
+    }
+
+    /// Add a word that is always restored when typed (case-insensitive)
+    ///
+    /// For English words auto-restore can't tell from Vietnamese ("mix" → "mĩ").
+    pub fn add_english_word(&mut self, word: &str) {
+        let word = word.trim().to_lowercase();
+        if word.is_empty() {
+            return;
+        }
+        if let Err(i) = self.english_words.binary_search(&word) {
+            self.english_words.insert(i, word);
+        }
+    }
+
+    /// Remove a user-added English word
+    pub fn remove_english_word(&mut self, word: &str) -> bool {
+        match self
+            .english_words
+            .binary_search(&word.trim().to_lowercase())
+        {
+            Ok(i) => {
+                self.english_words.remove(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// User-added English words, sorted
+    pub fn english_words(&self) -> &[String] {
+        &self.english_words
+    }
+
+    /// Set whether transforms must lead to a real syllable
+    ///
+    /// A tone, mark or stroke that makes the word unable to become a syllable
+    /// in use is typed as a plain letter instead ("quuyns" stays as typed).
+    /// With English auto-restore on, words left as non-syllables are restored.
+    pub fn set_strict_syllables(&mut self, enabled: bool) {
+        self.strict_syllables = enabled;
+    }
+
+    /// Set whether to suggest diacritics for words typed without them
+    ///
+    /// Committing "toi" with space offers "tôi", "tối", ... through
+    /// `suggestions`; the word stays as typed until one is accepted.
+    pub fn set_auto_accent(&mut self, enabled: bool) {
+        self.auto_accent = enabled;
+        if !enabled {
+            self.suggestions = None;
+        }
+    }
+
     /// Set whether to enable auto-capitalize after sentence-ending punctuation
     pub fn set_auto_capitalize(&mut self, enabled: bool) {
         self.auto_capitalize = enabled;
@@ -405,6 +671,14 @@ impl Engine {
         }
     }
 
+    /// Set whether Left arrow moves a caret inside the word
+    ///
+    /// Only for hosts that keep arrows away from `clear_all` and apply edits
+    /// around their caret (see `on_key_edit`). Off: arrows end the word.
+    pub fn set_caret_tracking(&mut self, enabled: bool) {
+        self.caret_tracking = enabled;
+    }
+
     pub fn shortcuts(&self) -> &ShortcutTable {
         &self.shortcuts
     }
@@ -432,6 +706,31 @@ impl Engine {
         self.on_key_ext(key, caps, ctrl, false)
     }
 
+    /// Handle a character already resolved by the host keyboard layout
+    ///
+    /// Layout-independent alternative to `on_key_ext`: AZERTY/Dvorak/Colemak
+    /// hosts pass the produced character instead of a macOS keycode.
+    /// Case comes from the character itself; shifted symbols map to Shift+key.
+    /// Characters outside the US layout (é, €, ...) end the current word.
+    ///
+    /// # Arguments
+    /// * `ch` - character produced by the key press ('\x08' = backspace, '\x1b' = ESC)
+    /// * `modifiers` - bit set of `MOD_*` flags
+    pub fn on_char(&mut self, ch: char, modifiers: u8) -> Result {
+        let ctrl = modifiers & MOD_CTRL != 0;
+        match crate::utils::char_to_key_ext(ch) {
+            Some((key, caps, shift)) => self.on_key_ext(key, caps, ctrl, shift),
+            None => {
+                // Unknown to Telex/VNI: acts like a word break, char passes through
+                self.reset();
+                self.word_history.clear();
+                self.spaces_after_commit = 0;
+                self.shortcut_prefix.clear();
+                Result::none()
+            }
+        }
+    }
+
     /// Check if key+shift combo is a raw mode prefix character
     /// Raw prefixes: @ # : /
     #[allow(dead_code)] // TEMP DISABLED
@@ -460,18 +759,97 @@ impl Engine {
     /// * `ctrl` - true if Cmd/Ctrl/Alt is pressed (bypasses IME)
     /// * `shift` - true if Shift key is pressed (for symbols like @, #, $)
     pub fn on_key_ext(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        let accent = self.accent_begin(key, ctrl);
+        let result = self.key_ext(key, caps, ctrl, shift);
+        self.accent_end(accent, &result);
+        self.record_key(key, caps, ctrl, shift, &result);
+        result
+    }
+
+    /// `on_key_ext` without recording
+    fn key_ext(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
+        self.long_output = None;
+        self.caret_edit = None;
+        let undo = self.undo_begin(key, ctrl, shift);
+        if self.output_charset == Charset::Unicode {
+            let result = self.handle_key(key, caps, ctrl, shift);
+            self.undo_end(undo, key, caps, shift, &result);
+            return result;
+        }
+        // Deleted chars may span several code units once encoded
+        let before: Vec<char> = self.buf.to_full_string().chars().collect();
+        let caret = self.caret.unwrap_or(before.len()).min(before.len());
+        let result = self.handle_key(key, caps, ctrl, shift);
+        self.undo_end(undo, key, caps, shift, &result);
+        let output = self.output_charset;
+        if let Some((after, cursor)) = self.caret_edit.as_mut() {
+            let after_end = (caret + *after).min(before.len());
+            *after = before[caret..after_end]
+                .iter()
+                .map(|&c| charset::encoded_len(c, output))
+                .sum();
+            // Cursor counts sent chars, which may be beyond `Result` (long_output)
+            let sent: Vec<char> = match &self.long_output {
+                Some(text) => text.chars().collect(),
+                None => result.chars[..result.count as usize]
+                    .iter()
+                    .filter_map(|&c| char::from_u32(c))
+                    .collect(),
+            };
+            *cursor = sent[..(*cursor).min(sent.len())]
+                .iter()
+                .map(|&c| charset::encoded_len(c, output))
+                .sum();
+        }
+        if let Some(text) = self.long_output.as_mut() {
+            *text = charset::encode(text, output);
+        }
+        let head: String = before[..caret].iter().collect();
+        encode_result(result, &head, output)
+    }
+
+    /// Handle key event, returning a variable-length edit
+    ///
+    /// Same as `on_key_ext`, but the inserted text is not capped at MAX
+    /// (long shortcut expansions come through whole), and edits inside a
+    /// word may delete after the caret.
+    pub fn on_key_edit(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Edit {
+        self.can_delete_after = true;
+        let result = self.on_key_ext(key, caps, ctrl, shift);
+        self.can_delete_after = false;
+        let mut edit = Edit::from(&result);
+        if let Some((after, cursor)) = self.caret_edit.take() {
+            edit.delete_after = after;
+            edit.cursor = Some(cursor);
+        }
+        if let Some(text) = self.long_output.take() {
+            edit.text = text;
+        }
+        edit
+    }
+
+    /// Key handling in Unicode (see `on_key_ext`)
+    fn handle_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool) -> Result {
         // Issue #129: Process shortcuts even when IME is disabled
         // Only bypass completely for Ctrl/Cmd modifier keys
         if ctrl {
-            self.clear();
+            self.reset();
             self.word_history.clear();
             self.spaces_after_commit = 0;
             return Result::none();
         }
 
-        // When IME is disabled, only process break keys for shortcuts
+        // Password fields: nothing is transformed or expanded
+        let purpose = self.shortcuts.purpose();
+        if purpose == InputPurpose::Password {
+            self.reset_all();
+            self.shortcut_prefix.clear();
+            return Result::none();
+        }
+
+        // When IME is disabled (or in a terminal), only process break keys for shortcuts
         // Skip Vietnamese processing (tones, marks, etc.) but allow shortcuts to work
-        if !self.enabled {
+        if !self.enabled || purpose == InputPurpose::Terminal {
             // Clear Vietnamese state but keep processing break keys for shortcuts
             self.buf.clear();
             self.raw_input.clear();
@@ -493,10 +871,9 @@ impl Engine {
                         input_method,
                     ) {
                         // Found a match! Send the replacement
-                        let output: Vec<char> = m.output.chars().collect();
                         let backspace_count = (m.backspace_count as u8).saturating_sub(1);
                         self.shortcut_prefix.clear();
-                        return Result::send_consumed(backspace_count, &output);
+                        return self.send_expansion(backspace_count, &m, true);
                     }
                     // No match yet, keep accumulating
                     return Result::none();
@@ -508,13 +885,28 @@ impl Engine {
             return Result::none();
         }
 
+        // Caret moved inside the word: compose around it
+        if let Some(caret) = self.caret {
+            return self.handle_key_at_caret(key, caps, shift, caret);
+        }
+
+        // Left arrow steps into the word instead of ending it
+        if key == keys::LEFT && !shift && self.caret_tracking && !self.buf.is_empty() {
+            self.caret = Some(self.buf.len() - 1);
+            self.last_transform = None;
+            self.pending_breve_pos = None;
+            self.pending_u_horn_pos = None;
+            self.sync_raw_input();
+            return Result::none();
+        }
+
         // Check for word boundary shortcuts ONLY on SPACE
         // Also auto-restore invalid Vietnamese to raw English
         if key == keys::SPACE {
             // First check for shortcut
             let shortcut_result = self.try_word_boundary_shortcut();
             if shortcut_result.action != 0 {
-                self.clear();
+                self.reset();
                 return shortcut_result;
             }
 
@@ -542,7 +934,7 @@ impl Engine {
                 self.spaces_after_commit = self.spaces_after_commit.saturating_add(1);
             }
             self.auto_capitalize_used = false; // Reset on word commit
-            self.clear();
+            self.reset();
             return restore_result;
         }
 
@@ -554,12 +946,17 @@ impl Engine {
             } else {
                 Result::none()
             };
-            self.clear();
+            self.reset();
             self.word_history.clear();
             self.spaces_after_commit = 0;
             return result;
         }
 
+        // VIQR: punctuation modifiers (' ` ? ~ . ^ ( +) compose the current word
+        if let Some(result) = self.try_symbol_modifier(key, caps, shift) {
+            return result;
+        }
+
         // Other break keys (punctuation, arrows, etc.)
         // Also trigger auto-restore for invalid Vietnamese before clearing
         // Use is_break_ext to handle shifted symbols like @, !, #, etc.
@@ -597,10 +994,9 @@ impl Engine {
                         // Found a match! Send the replacement with key_consumed flag
                         // Note: backspace_count - 1 because current key hasn't been typed yet
                         // Example: "->" trigger has backspace_count=2, but only '-' is on screen
-                        let output: Vec<char> = m.output.chars().collect();
                         let backspace_count = (m.backspace_count as u8).saturating_sub(1);
                         self.shortcut_prefix.clear();
-                        return Result::send_consumed(backspace_count, &output);
+                        return self.send_expansion(backspace_count, &m, true);
                     }
 
                     // Auto-capitalize: set pending if sentence-ending (! or ?)
@@ -622,7 +1018,7 @@ impl Engine {
             self.auto_capitalize_used = false; // Reset on word boundary
 
             let restore_result = self.try_auto_restore_on_break();
-            self.clear();
+            self.reset();
             self.word_history.clear();
             self.spaces_after_commit = 0;
 
@@ -693,11 +1089,11 @@ impl Engine {
         // This allows "cha" + restore + "f" → "chà" (f is mark key)
         // But "cha" + restore + "m" → "m..." (m is consonant, start fresh)
         if self.restored_pending_clear && keys::is_letter(key) {
-            let m = input::get(self.method);
+            let m = self.method_ref();
             let is_mark_or_tone = m.mark(key).is_some() || m.tone(key).is_some();
             if keys::is_consonant(key) && !is_mark_or_tone {
                 // Regular consonant (not mark/tone key) = user starting new word
-                self.clear();
+                self.reset();
             }
             // Reset flag regardless - user is now actively typing
             self.restored_pending_clear = false;
@@ -736,9 +1132,187 @@ impl Engine {
         result
     }
 
+    /// Apply a VIQR modifier: a punctuation key, or the 'd' of "dd"
+    ///
+    /// Returns None when the key should stay a word break: empty buffer,
+    /// not a modifier, or nothing in the word can take it ("ok." → "ok.").
+    /// The key is always consumed; a revert ("a''" → "a'") emits it as a
+    /// literal and ends the word, since the buffer only holds letters.
+    fn try_symbol_modifier(&mut self, key: u16, caps: bool, shift: bool) -> Option<Result> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        // VIQR "dd" → "đ": the second 'd' is a letter too, so it only strokes
+        // the 'd' right before it ("dad" stays) or reverts that stroke
+        if self.method == 2 && key == keys::D {
+            let after_d = self.buf.iter().position(|c| c.key == keys::D && !c.stroke)
+                == Some(self.buf.len() - 1);
+            if !after_d && self.last_transform != Some(Transform::Stroke(key)) {
+                return None;
+            }
+            self.raw_input.push((key, caps, shift));
+            let result = self.try_stroke(key);
+            if result.is_none() {
+                self.raw_input.pop();
+            }
+            return result;
+        }
+        if !keys::is_break_ext(key, shift) {
+            return None;
+        }
+        let m = self.method_ref();
+        let mkey = keys::with_shift(key, shift);
+        let len = self.buf.len();
+
+        let result = if m.stroke(mkey) {
+            self.try_stroke(key)
+        } else if let Some(tone_type) = m.tone(mkey) {
+            self.try_tone(key, caps, tone_type, m.tone_targets(mkey))
+        } else if let Some(mark_val) = m.mark(mkey) {
+            self.try_mark(key, caps, mark_val)
+        } else if m.remove(mkey) {
+            self.try_remove()
+        } else {
+            None
+        }?;
+
+        if self.buf.len() > len {
+            // Revert pushed the key as a char: re-send the word with the literal symbol
+            self.buf.pop();
+            let mut output: Vec<char> = self.buf.to_full_string().chars().collect();
+            output.extend(break_key_to_char(key, shift));
+            self.reset();
+            self.word_history.clear();
+            self.spaces_after_commit = 0;
+            return Some(Result::send_consumed(len as u8, &output));
+        }
+
+        self.raw_input.push((key, caps, shift));
+        let mut result = result;
+        result.flags |= FLAG_KEY_CONSUMED;
+        Some(result)
+    }
+
+    /// Handle a key while the caret is inside the word
+    ///
+    /// Left/Right move the caret and DELETE removes the char before it.
+    /// Modifier keys apply to the whole syllable ("ti|eng" + e → "tiê|ng"),
+    /// other letters are inserted at the caret. Any other key ends the word.
+    fn handle_key_at_caret(&mut self, key: u16, caps: bool, shift: bool, caret: usize) -> Result {
+        let len = self.buf.len();
+        match key {
+            keys::LEFT | keys::RIGHT if !shift => {
+                self.last_transform = None;
+                match caret.checked_add_signed(if key == keys::LEFT { -1 } else { 1 }) {
+                    Some(c) if c < len => self.caret = Some(c),
+                    Some(_) => self.caret = None, // back at the end of the word
+                    None => self.reset_all(),     // left the word
+                }
+                return Result::none();
+            }
+            keys::DELETE if caret > 0 => {
+                self.buf.remove(caret - 1);
+                self.last_transform = None;
+                if self.buf.is_empty() {
+                    self.reset();
+                } else {
+                    self.caret = Some(caret - 1);
+                    self.sync_raw_input();
+                }
+                return Result::none();
+            }
+            _ => {}
+        }
+
+        let m = self.method_ref();
+        let mkey = keys::with_shift(key, shift);
+        let is_modifier =
+            m.stroke(mkey) || m.tone(mkey).is_some() || m.mark(mkey).is_some() || m.remove(mkey);
+        let is_char = keys::is_letter(key) || (self.is_modifier_digit(key) && !shift);
+        if !(is_char || is_modifier) || len >= MAX {
+            self.reset_all();
+            return Result::none();
+        }
+
+        let old: Vec<char> = self.buf.to_full_string().chars().collect();
+        self.raw_input.push((key, caps, shift));
+        if is_modifier {
+            // Same order as process(); output comes from diffing the whole word
+            let mut applied = None;
+            if m.stroke(mkey) {
+                applied = self.try_stroke(key);
+            }
+            if let (None, Some(tone_type)) = (&applied, m.tone(mkey)) {
+                applied = self.try_tone(key, caps, tone_type, m.tone_targets(mkey));
+            }
+            if let (None, Some(mark_val)) = (&applied, m.mark(mkey)) {
+                applied = self.try_mark(key, caps, mark_val);
+            }
+            if applied.is_none() && m.remove(mkey) {
+                self.try_remove();
+            }
+        }
+
+        let mut new_caret = caret;
+        if self.buf.len() == len + 1 && self.buf.last().is_some_and(|c| c.key == key) {
+            // A revert appended the key as a letter: it belongs at the caret
+            if let Some(c) = self.buf.pop() {
+                self.buf.insert(caret, c);
+                new_caret += 1;
+            }
+        } else if self.buf.to_full_string().chars().eq(old.iter().copied()) {
+            if !is_char {
+                // Modifier symbol with nothing to modify (VIQR "ok|." stays a break)
+                self.reset_all();
+                return Result::none();
+            }
+            // Plain letter: insert it, the host types it at its caret
+            self.buf.insert(caret, Char::new(key, caps));
+            self.caret = Some(caret + 1);
+            self.sync_raw_input();
+            return Result::none();
+        }
+
+        self.sync_raw_input();
+        let new_caret = new_caret.min(self.buf.len());
+        self.caret = (new_caret < self.buf.len()).then_some(new_caret);
+        self.send_around_caret(&old, caret, new_caret)
+    }
+
+    /// Replace the part of the word that changed around the caret
+    ///
+    /// `old` is the word before the key, `old_caret`/`new_caret` the caret
+    /// before and after it. Changes before the caret fit in `Result`. Changes
+    /// past it need `Edit.delete_after`: legacy callers get the key passed
+    /// through and the word ended instead, as if the caret had left it.
+    fn send_around_caret(&mut self, old: &[char], old_caret: usize, new_caret: usize) -> Result {
+        let new: Vec<char> = self.buf.to_full_string().chars().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(old.len().min(new.len()) - prefix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let start = prefix.min(old_caret);
+        let old_end = (old.len() - suffix).max(old_caret);
+        let new_end = new.len() - (old.len() - old_end);
+        let delete_after = old_end - old_caret;
+        if delete_after > 0 {
+            if !self.can_delete_after {
+                self.reset_all();
+                return Result::none();
+            }
+            self.caret_edit = Some((delete_after, new_caret.clamp(start, new_end) - start));
+        }
+        Result::send((old_caret - start) as u8, &new[start..new_end])
+    }
+
     /// Main processing pipeline - pattern-based
     fn process(&mut self, key: u16, caps: bool, shift: bool) -> Result {
-        let m = input::get(self.method);
+        let m = self.method_ref();
 
         // Handle pending mark revert pop: if previous key was a mark revert (like "ss"),
         // and THIS key is a consonant, pop the consumed modifier from raw_input.
@@ -824,20 +1398,27 @@ impl Engine {
         // User wants the symbol (@ for Shift+2, # for Shift+3, etc.), not VNI marks
         let skip_vni_modifiers = self.method == 1 && shift && keys::is_number(key);
 
+        // Modifier lookup key: Shift+symbol differs from the plain key in method tables
+        let mkey = keys::with_shift(key, shift);
+
         // Check modifiers by scanning buffer for patterns
+        // Strict syllables: word to go back to when a modifier leads nowhere
+        let mut before = self.strict_syllables.then(|| self.word_state());
 
         // 1. Stroke modifier (d → đ)
-        if !skip_vni_modifiers && m.stroke(key) {
-            if let Some(result) = self.try_stroke(key) {
+        if !skip_vni_modifiers && m.stroke(mkey) {
+            let result = self.try_stroke(key);
+            if let Some(result) = self.keep_if_known(&mut before, result) {
                 return result;
             }
         }
 
         // 2. Tone modifier (circumflex, horn, breve)
         if !skip_vni_modifiers {
-            if let Some(tone_type) = m.tone(key) {
-                let targets = m.tone_targets(key);
-                if let Some(result) = self.try_tone(key, caps, tone_type, targets) {
+            if let Some(tone_type) = m.tone(mkey) {
+                let targets = m.tone_targets(mkey);
+                let result = self.try_tone(key, caps, tone_type, targets);
+                if let Some(result) = self.keep_if_known(&mut before, result) {
                     return result;
                 }
             }
@@ -845,8 +1426,9 @@ impl Engine {
 
         // 3. Mark modifier
         if !skip_vni_modifiers {
-            if let Some(mark_val) = m.mark(key) {
-                if let Some(result) = self.try_mark(key, caps, mark_val) {
+            if let Some(mark_val) = m.mark(mkey) {
+                let result = self.try_mark(key, caps, mark_val);
+                if let Some(result) = self.keep_if_known(&mut before, result) {
                     return result;
                 }
             }
@@ -855,7 +1437,7 @@ impl Engine {
         // 4. Remove modifier
         // Only consume key if there's something to remove; otherwise fall through to normal letter
         // This allows shortcuts like "zz" to work when buffer has no marks/tones to remove
-        if !skip_vni_modifiers && m.remove(key) {
+        if !skip_vni_modifiers && m.remove(mkey) {
             if let Some(result) = self.try_remove() {
                 return result;
             }
@@ -864,7 +1446,8 @@ impl Engine {
         // 5. In Telex: "w" as vowel "ư" when valid Vietnamese context
         // Examples: "w" → "ư", "nhw" → "như", but "kw" → "kw" (invalid)
         if self.method == 0 && key == keys::W {
-            if let Some(result) = self.try_w_as_vowel(caps) {
+            let result = self.try_w_as_vowel(caps);
+            if let Some(result) = self.keep_if_known(&mut before, result) {
                 return result;
             }
         }
@@ -873,6 +1456,32 @@ impl Engine {
         self.handle_normal_letter(key, caps)
     }
 
+    /// Keep a modifier's result only if the word can still become a syllable
+    ///
+    /// `before` is the word captured when `strict_syllables` is on. Results
+    /// without diacritics (reverts like "ss" → "s") are always kept.
+    fn keep_if_known(
+        &mut self,
+        before: &mut Option<WordState>,
+        result: Option<Result>,
+    ) -> Option<Result> {
+        if before.is_none() || result.is_none() {
+            return result;
+        }
+        let plain = self
+            .buf
+            .iter()
+            .all(|c| c.tone == 0 && c.mark == 0 && !c.stroke);
+        if plain || is_known_syllable_prefix(&self.buf.to_full_string()) {
+            return result;
+        }
+        // Later modifiers start from the same word
+        let word = before.take()?;
+        self.restore_word_state(word);
+        *before = Some(self.word_state());
+        None
+    }
+
     /// Try word boundary shortcuts (triggered by space, punctuation, etc.)
     fn try_word_boundary_shortcut(&mut self) -> Result {
         // Issue #107: Allow shortcuts with special char prefix (like "#fne")
@@ -901,14 +1510,35 @@ impl Engine {
             self.shortcuts
                 .try_match_for_method(&full_trigger, Some(' '), true, input_method)
         {
-            let output: Vec<char> = m.output.chars().collect();
             // backspace_count = trigger.len() which already includes prefix (e.g., "#fne" = 4)
-            return Result::send(m.backspace_count as u8, &output);
+            return self.send_expansion(m.backspace_count as u8, &m, false);
         }
 
         Result::none()
     }
 
+    /// Build the result for a shortcut expansion
+    ///
+    /// `Result` holds at most MAX chars; longer text is kept for `on_key_edit`,
+    /// which also reports the `{cursor}` position.
+    fn send_expansion(&mut self, backspace: u8, m: &ShortcutMatch, consumed: bool) -> Result {
+        let chars: Vec<char> = m.output.chars().collect();
+        // Legacy charsets may need several code units per char
+        let output = self.output_charset;
+        let encoded_len: usize = chars.iter().map(|&c| charset::encoded_len(c, output)).sum();
+        if encoded_len > MAX {
+            self.long_output = Some(m.output.clone());
+        }
+        if m.cursor_back > 0 {
+            self.caret_edit = Some((0, chars.len().saturating_sub(m.cursor_back)));
+        }
+        if consumed {
+            Result::send_consumed(backspace, &chars)
+        } else {
+            Result::send(backspace, &chars)
+        }
+    }
+
     /// Try "w" as vowel "ư" in Telex mode
     ///
     /// Rules:
@@ -2475,9 +3105,9 @@ impl Engine {
         }
 
         self.last_transform = None;
-        // Add letters to buffer, and numbers in VNI mode (for pass-through after revert)
+        // Add letters to buffer, and modifier digits (VNI) for pass-through after revert
         // This ensures buffer.len() stays in sync with screen chars for correct backspace count
-        if keys::is_letter(key) || (self.method == 1 && keys::is_number(key)) {
+        if keys::is_letter(key) || self.is_modifier_digit(key) {
             // Add the letter/number to buffer
             self.buf.push(Char::new(key, caps));
 
@@ -2615,7 +3245,7 @@ impl Engine {
             // not true consonants. User typing "đườ" + 's' wants to add sắc mark, not restore.
             //
             // Only run if english_auto_restore is enabled (experimental feature)
-            let im = input::get(self.method);
+            let im = self.method_ref();
             let is_mark_key = im.mark(key).is_some();
             if self.english_auto_restore
                 && keys::is_consonant(key)
@@ -2789,6 +3419,20 @@ impl Engine {
     /// Note: Does NOT clear word_history to preserve backspace-after-space feature
     /// Also restores pending_capitalize if auto_capitalize was used (for selection-delete)
     pub fn clear(&mut self) {
+        self.reset();
+        self.record_clear(false);
+    }
+
+    /// Clear everything including word history
+    /// Used when cursor position changes (mouse click, arrow keys, etc.)
+    /// to prevent accidental restore from stale history
+    pub fn clear_all(&mut self) {
+        self.reset_all();
+        self.record_clear(true);
+    }
+
+    /// `clear` without recording (the engine's own word ends)
+    fn reset(&mut self) {
         // Restore pending_capitalize if auto_capitalize was used
         // This handles selection-delete: user selects and deletes text,
         // we should restore pending state so next letter is capitalized
@@ -2809,15 +3453,17 @@ impl Engine {
         self.had_vowel_triggered_circumflex = false;
         self.restored_pending_clear = false;
         self.shortcut_prefix.clear();
+        self.caret = None;
+        self.undo.clear();
+        self.undo_keys.clear();
     }
 
-    /// Clear everything including word history
-    /// Used when cursor position changes (mouse click, arrow keys, etc.)
-    /// to prevent accidental restore from stale history
-    pub fn clear_all(&mut self) {
-        self.clear();
+    /// `clear_all` without recording
+    fn reset_all(&mut self) {
+        self.reset();
         self.word_history.clear();
         self.spaces_after_commit = 0;
+        self.suggestions = None;
     }
 
     /// Get the full composed buffer as a Vietnamese string with diacritics.
@@ -2832,7 +3478,7 @@ impl Engine {
     /// Used when native app detects cursor at word boundary and wants to edit.
     /// Parses Vietnamese characters back to buffer components.
     pub fn restore_word(&mut self, word: &str) {
-        self.clear();
+        self.reset();
         for c in word.chars() {
             if let Some(parsed) = chars::parse_char(c) {
                 let mut ch = Char::new(parsed.key, parsed.caps);
@@ -2845,12 +3491,103 @@ impl Engine {
         }
     }
 
-    /// Check if buffer has transforms and is invalid Vietnamese
+    /// Re-enter composition from the text around the host caret
+    ///
+    /// For hosts that expose surrounding text (IBus, Fcitx) after focus or
+    /// caret changes. `cursor` is a char index into `text`:
+    /// - The word touching the cursor becomes the buffer ("tiê|ng" keeps the caret inside)
+    /// - A word followed only by spaces goes to history, so backspace re-enters it
+    /// - Text ending a sentence (". ", "!\n") arms auto-capitalize
+    pub fn set_surrounding_text(&mut self, text: &str, cursor: usize) {
+        self.reset_all();
+        self.pending_capitalize = false;
+        self.auto_capitalize_used = false;
+
+        let text: Vec<char> = text.chars().collect();
+        let cursor = cursor.min(text.len());
+        let (before, after) = text.split_at(cursor);
+        let is_word_char = |c: &char| c.is_alphabetic() && chars::parse_char(*c).is_some();
+
+        let head = before.iter().rev().take_while(|c| is_word_char(c)).count();
+        let tail = after.iter().take_while(|c| is_word_char(c)).count();
+        if head + tail > 0 {
+            if head + tail > MAX {
+                return;
+            }
+            let start = cursor - head;
+            self.buf = word_buffer(&text[start..cursor + tail]);
+            self.sync_raw_input();
+            self.caret = (tail > 0).then_some(head);
+            // "149k": digits or foreign letters glued to the word, like typing them
+            self.has_non_letter_prefix =
+                before[..start].last().is_some_and(|c| c.is_alphanumeric());
+            return;
+        }
+
+        let spaces = before.iter().rev().take_while(|&&c| c == ' ').count();
+        let end = before.len() - spaces;
+        let word_len = before[..end]
+            .iter()
+            .rev()
+            .take_while(|c| is_word_char(c))
+            .count();
+        if spaces > 0 && word_len > 0 && word_len <= MAX {
+            self.word_history
+                .push(word_buffer(&before[end - word_len..end]));
+            self.spaces_after_commit = spaces.min(u8::MAX as usize) as u8;
+        }
+
+        if self.auto_capitalize {
+            let last = before.iter().rev().find(|&&c| c != ' ' && c != '\t');
+            self.pending_capitalize = matches!(last, Some('.' | '!' | '?' | '\n' | '\r'));
+        }
+    }
+
+    /// Check if the word should be restored to its raw keys (English)
+    /// Returns the raw chars if restore is needed, None otherwise
+    ///
+    /// Heuristics first, then word lists once the word is complete:
+    /// - User words (`add_english_word`) always restore
+    /// - With the dictionary on, a heuristic restore of a Vietnamese word in
+    ///   use is dropped unless the raw word is common English ("sax" keeps
+    ///   "sã", "fox" is restored)
+    fn should_auto_restore(&self, is_word_complete: bool) -> Option<Vec<char>> {
+        if !self.english_auto_restore || self.raw_input.is_empty() || self.buf.is_empty() {
+            return None;
+        }
+        if !is_word_complete {
+            return self.english_heuristics(false);
+        }
+
+        let raw = self.build_raw_chars()?;
+        let word: String = raw.iter().flat_map(|c| c.to_lowercase()).collect();
+        if self.had_any_transform && self.english_words.binary_search(&word).is_ok() {
+            return Some(raw);
+        }
+        // A transformed word that is no syllable in use can't be Vietnamese
+        if self.strict_syllables
+            && self.had_any_transform
+            && !is_known_syllable(&self.buf.to_full_string())
+        {
+            return Some(raw);
+        }
+        let restore = self.english_heuristics(true)?;
+        // A Vietnamese word whose keys spell no common English word stays
+        if self.english_dictionary
+            && !english::contains(&word)
+            && is_known_syllable(&self.buf.to_full_string())
+        {
+            return None;
+        }
+        Some(restore)
+    }
+
+    /// Guess from letter patterns whether the word is English
     /// Returns the raw chars if restore is needed, None otherwise
     ///
     /// `is_word_complete`: true when called on space/break (word is complete)
     ///                     false when called mid-word (during typing)
-    fn should_auto_restore(&self, is_word_complete: bool) -> Option<Vec<char>> {
+    fn english_heuristics(&self, is_word_complete: bool) -> Option<Vec<char>> {
         // Only run auto-restore if the feature is enabled
         if !self.english_auto_restore {
             return None;
@@ -3061,7 +3798,11 @@ impl Engine {
             let mut chars: Vec<char> = self
                 .raw_input
                 .iter()
-                .filter_map(|&(key, caps, shift)| utils::key_to_char_ext(key, caps, shift))
+                .filter_map(|&(key, caps, shift)| {
+                    // VIQR symbol modifiers are recorded too (break keys)
+                    utils::key_to_char_ext(key, caps, shift)
+                        .or_else(|| break_key_to_char(key, shift))
+                })
                 .collect();
 
             // Collapse vowel patterns for English restore (Telex circumflex patterns)
@@ -4071,6 +4812,12 @@ impl Engine {
         Result::send(backspace, &raw_chars)
     }
 
+    /// Rebuild raw_input from the current buffer (after edits inside the word)
+    fn sync_raw_input(&mut self) {
+        let buf = self.buf.clone();
+        self.restore_raw_input_from_buffer(&buf);
+    }
+
     /// Restore raw_input from buffer (for ESC restore to work after backspace-restore)
     fn restore_raw_input_from_buffer(&mut self, buf: &Buffer) {
         self.raw_input.clear();
@@ -4082,8 +4829,10 @@ impl Engine {
 
 #[cfg(test)]
 mod tests {
-    use super::Engine;
-    use crate::utils::{telex, type_word, vni};
+    use super::shortcut::{InputPurpose, Shortcut};
+    use super::{Action, Engine, MOD_CTRL};
+    use crate::data::keys;
+    use crate::utils::{telex, type_word, viqr, vni};
 
     const TELEX_BASIC: &[(&str, &str)] = &[
         ("as", "á"),
@@ -4123,6 +4872,23 @@ mod tests {
         ("d9", "đ"),
     ];
 
+    const VIQR_BASIC: &[(&str, &str)] = &[
+        ("a'", "á"),
+        ("a`", "à"),
+        ("a?", "ả"),
+        ("a~", "ã"),
+        ("a.", "ạ"),
+        ("a^", "â"),
+        ("a(", "ă"),
+        ("a(m", "ăm"),
+        ("a('", "ắ"),
+        ("e^", "ê"),
+        ("o^", "ô"),
+        ("o+", "ơ"),
+        ("u+", "ư"),
+        ("dd", "đ"),
+    ];
+
     const TELEX_COMPOUND: &[(&str, &str)] =
         &[("duocw", "dươc"), ("nguoiw", "ngươi"), ("tuoiws", "tưới")];
 
@@ -4160,6 +4926,11 @@ mod tests {
         vni(VNI_BASIC);
     }
 
+    #[test]
+    fn test_viqr_basic() {
+        viqr(VIQR_BASIC);
+    }
+
     #[test]
     fn test_telex_compound() {
         telex(TELEX_COMPOUND);
@@ -4192,4 +4963,402 @@ mod tests {
     fn test_telex_normal() {
         telex(TELEX_NORMAL);
     }
+
+    #[test]
+    fn test_on_char_matches_keycodes() {
+        for (method, input) in [(0, "Vieejt Nam dduwowcj"), (1, "Vie65t Nam d9u7o75c")] {
+            let mut by_key = Engine::new();
+            let mut by_char = Engine::new();
+            by_key.set_method(method);
+            by_char.set_method(method);
+
+            let mut screen = String::new();
+            for ch in input.chars() {
+                let r = by_char.on_char(ch, 0);
+                if r.action == Action::Send as u8 {
+                    for _ in 0..r.backspace {
+                        screen.pop();
+                    }
+                    screen.extend((0..r.count as usize).filter_map(|i| char::from_u32(r.chars[i])));
+                } else {
+                    screen.push(ch);
+                }
+            }
+            assert_eq!(screen, type_word(&mut by_key, input), "method {}", method);
+        }
+    }
+
+    #[test]
+    fn test_on_char_shifted_symbols_and_unknown() {
+        let mut e = Engine::new();
+        e.set_method(1); // VNI: '@' must not act as huyền mark
+        e.on_char('a', 0);
+        let r = e.on_char('@', 0);
+        assert_eq!(r.action, Action::None as u8);
+        assert_eq!(e.get_buffer_string(), "");
+
+        // Non-US char commits the word, next char starts fresh
+        let mut e = Engine::new();
+        e.on_char('a', 0);
+        e.on_char('é', 0);
+        e.on_char('s', 0);
+        assert_eq!(e.get_buffer_string(), "s");
+
+        // Ctrl bypasses and clears
+        e.on_char('a', 0);
+        assert_eq!(e.on_char('s', MOD_CTRL).action, Action::None as u8);
+        assert_eq!(e.get_buffer_string(), "");
+    }
+
+    #[test]
+    fn test_table_method() {
+        let table = crate::input::TableMethod::parse(
+            "[marks]\nq = sac\nz = huyen\n[tones]\nk = \"circumflex a e o\"\n[keys]\nstroke = \"9\"\n",
+        )
+        .unwrap();
+        let mut e = Engine::new();
+        e.load_method(table);
+        assert_eq!(type_word(&mut e, "vietkq"), "viết");
+        e.clear();
+        assert_eq!(type_word(&mut e, "d9oz"), "đò");
+        // Digits that aren't modifiers stay out of the word
+        e.clear();
+        type_word(&mut e, "ta5");
+        assert!(!e.get_buffer_string().contains('5'));
+
+        // Switching away and back keeps the loaded table
+        e.set_method(0);
+        e.clear();
+        assert_eq!(type_word(&mut e, "as"), "á");
+        e.set_method(crate::input::TABLE);
+        e.clear();
+        assert_eq!(type_word(&mut e, "aq"), "á");
+    }
+
+    #[test]
+    fn test_output_charset_backspace() {
+        use crate::data::charset::{self, Charset};
+
+        for output in [
+            Charset::VniWindows,
+            Charset::Tcvn3,
+            Charset::Viqr,
+            Charset::Nfd,
+        ] {
+            for input in ["vieejt", "dduowngf", "Nguyeenx", "hoaf", "thuwowngr"] {
+                let mut e = Engine::new();
+                e.set_output_charset(output);
+
+                // Screen holds encoded code units: backspace must remove whole chars
+                let mut screen: Vec<char> = Vec::new();
+                for ch in input.chars() {
+                    let r = e.on_char(ch, 0);
+                    if r.action == Action::Send as u8 {
+                        screen.truncate(screen.len() - r.backspace as usize);
+                        screen.extend(
+                            (0..r.count as usize).filter_map(|i| char::from_u32(r.chars[i])),
+                        );
+                    } else {
+                        screen.push(ch);
+                    }
+                }
+
+                let unicode = type_word(&mut Engine::new(), input);
+                let expected = charset::encode(&unicode, output);
+                assert_eq!(
+                    screen.iter().collect::<String>(),
+                    expected,
+                    "{:?} {}",
+                    output,
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_on_key_edit_long_expansion() {
+        let long = "Kính gửi quý khách. ".repeat(8);
+        let mut e = Engine::new();
+        e.shortcuts_mut()
+            .add(super::shortcut::Shortcut::new("kg", long.trim_end()));
+
+        for key in [keys::K, keys::G] {
+            e.on_key_edit(key, false, false, false);
+        }
+        let edit = e.on_key_edit(keys::SPACE, false, false, false);
+        assert_eq!(edit.action, Action::Send as u8);
+        assert_eq!(edit.backspace, 2);
+        assert_eq!(edit.text, format!("{} ", long.trim_end()));
+        assert!(edit.text.chars().count() > super::MAX);
+
+        // Legacy result still works, capped at MAX
+        let mut e2 = Engine::new();
+        e2.shortcuts_mut()
+            .add(super::shortcut::Shortcut::new("kg", long.trim_end()));
+        e2.on_key(keys::K, false, false);
+        e2.on_key(keys::G, false, false);
+        let r = e2.on_key(keys::SPACE, false, false);
+        assert_eq!(r.count as usize, super::MAX);
+
+        // Fits in MAX chars, but not once encoded as VIQR
+        let long = "Kính gửi quý khách. ".repeat(3);
+        let mut e = Engine::new();
+        e.set_output_charset(crate::data::charset::Charset::Viqr);
+        e.shortcuts_mut()
+            .add(super::shortcut::Shortcut::new("kg", long.trim_end()));
+        for key in [keys::K, keys::G] {
+            e.on_key_edit(key, false, false, false);
+        }
+        let edit = e.on_key_edit(keys::SPACE, false, false, false);
+        assert!(long.chars().count() <= super::MAX);
+        assert_eq!(
+            edit.text,
+            crate::data::charset::encode(&long, crate::data::charset::Charset::Viqr)
+        );
+    }
+
+    fn result_text(r: &super::Result) -> String {
+        r.chars[..r.count as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect()
+    }
+
+    #[test]
+    fn test_caret_compose_inside_word() {
+        // Off by default: the arrow ends the word
+        let mut e = Engine::new();
+        type_word(&mut e, "ting");
+        e.on_key(keys::LEFT, false, false);
+        assert_eq!(e.get_buffer_string(), "");
+
+        let mut e = Engine::new();
+        e.set_caret_tracking(true);
+        type_word(&mut e, "ting");
+        e.on_key(keys::LEFT, false, false);
+        e.on_key(keys::LEFT, false, false);
+
+        // Plain letter is inserted at the caret and typed by the host
+        let r = e.on_key(keys::E, false, false);
+        assert_eq!(r.action, Action::None as u8);
+        assert_eq!(e.get_buffer_string(), "tieng");
+
+        // "tie|ng" + e → "tiê|ng": replaced before the caret
+        let r = e.on_key(keys::E, false, false);
+        assert_eq!((r.backspace, result_text(&r).as_str()), (1, "ê"));
+        let r = e.on_key(keys::S, false, false);
+        assert_eq!((r.backspace, result_text(&r).as_str()), (1, "ế"));
+        assert_eq!(e.get_buffer_string(), "tiếng");
+
+        // Back to the end: typing continues the same word
+        e.on_key(keys::RIGHT, false, false);
+        e.on_key(keys::RIGHT, false, false);
+        e.on_key(keys::SPACE, false, false);
+        assert_eq!(e.get_buffer_string(), "");
+    }
+
+    #[test]
+    fn test_caret_delete_and_leave_word() {
+        let mut e = Engine::new();
+        e.set_caret_tracking(true);
+        type_word(&mut e, "tieeng");
+        e.on_key(keys::LEFT, false, false);
+        e.on_key(keys::LEFT, false, false);
+        e.on_key(keys::DELETE, false, false);
+        assert_eq!(e.get_buffer_string(), "ting");
+
+        // Stepping left of the word ends it
+        for _ in 0..3 {
+            e.on_key(keys::LEFT, false, false);
+        }
+        assert_eq!(e.get_buffer_string(), "");
+        let r = e.on_key(keys::S, false, false);
+        assert_eq!(r.action, Action::None as u8);
+    }
+
+    #[test]
+    fn test_caret_edit_after_caret() {
+        // "ti|eng" + s: the mark lands after the caret
+        let mut e = Engine::new();
+        e.set_caret_tracking(true);
+        type_word(&mut e, "tieng");
+        for _ in 0..3 {
+            e.on_key_edit(keys::LEFT, false, false, false);
+        }
+        let edit = e.on_key_edit(keys::S, false, false, false);
+        assert_eq!(edit.action, Action::Send as u8);
+        assert_eq!((edit.backspace, edit.delete_after), (0, 1));
+        assert_eq!((edit.text.as_str(), edit.cursor), ("é", Some(0)));
+        assert_eq!(e.get_buffer_string(), "tiéng");
+
+        // Revert inserts the key at the caret: "ti|éng" + s → "tis|eng"
+        let edit = e.on_key_edit(keys::S, false, false, false);
+        assert_eq!((edit.backspace, edit.delete_after), (0, 1));
+        assert_eq!((edit.text.as_str(), edit.cursor), ("se", Some(1)));
+        assert_eq!(e.get_buffer_string(), "tiseng");
+
+        // Legacy Result can't delete after the caret: key passes through, word ends
+        let mut e = Engine::new();
+        e.set_caret_tracking(true);
+        type_word(&mut e, "tieng");
+        for _ in 0..3 {
+            e.on_key(keys::LEFT, false, false);
+        }
+        let r = e.on_key(keys::S, false, false);
+        assert_eq!(r.action, Action::None as u8);
+        assert_eq!(e.get_buffer_string(), "");
+    }
+
+    #[test]
+    fn test_surrounding_text_word_at_cursor() {
+        let mut e = Engine::new();
+        e.set_surrounding_text("xin chào tiê", 12);
+        assert_eq!(e.get_buffer_string(), "tiê");
+        type_word(&mut e, "ngs");
+        assert_eq!(e.get_buffer_string(), "tiếng");
+
+        // Cursor inside the word: caret stays there
+        e.set_surrounding_text("tiêng việt", 3);
+        let r = e.on_key(keys::S, false, false);
+        assert_eq!((r.backspace, result_text(&r).as_str()), (1, "ế"));
+        assert_eq!(e.get_buffer_string(), "tiếng");
+    }
+
+    #[test]
+    fn test_surrounding_text_history_and_capitalize() {
+        // Backspace over the spaces re-enters the previous word
+        let mut e = Engine::new();
+        e.set_surrounding_text("xin chao  ", 10);
+        assert_eq!(e.get_buffer_string(), "");
+        e.on_key(keys::DELETE, false, false);
+        e.on_key(keys::DELETE, false, false);
+        assert_eq!(e.get_buffer_string(), "chao");
+        e.on_key(keys::F, false, false);
+        assert_eq!(e.get_buffer_string(), "chào");
+
+        // Sentence end before the caret arms auto-capitalize
+        let mut e = Engine::new();
+        e.set_auto_capitalize(true);
+        e.set_surrounding_text("Xong. ", 6);
+        let r = e.on_key(keys::A, false, false);
+        assert_eq!(result_text(&r), "A");
+
+        e.set_surrounding_text("Xong, ", 6);
+        let r = e.on_key(keys::A, false, false);
+        assert_eq!(r.action, Action::None as u8);
+    }
+
+    #[test]
+    fn test_context() {
+        let mut e = Engine::new();
+        e.shortcuts_mut()
+            .add(Shortcut::new("sig", "Thân mến").in_app("com.apple.mail"));
+        e.shortcuts_mut().add(Shortcut::immediate("->", "→"));
+
+        e.set_context("com.apple.mail", InputPurpose::Text);
+        assert_eq!(type_word(&mut e, "sig "), "Thân mến ");
+        e.clear_all();
+        assert_eq!(type_word(&mut e, "vieetj"), "việt");
+
+        // Terminal: raw letters, symbol shortcuts still expand
+        e.set_context("com.apple.Terminal", InputPurpose::Terminal);
+        assert_eq!(type_word(&mut e, "sig "), "sig ");
+        e.clear_all();
+        assert_eq!(type_word(&mut e, "vieetj"), "vieetj");
+        e.clear_all();
+        assert_eq!(type_word(&mut e, "->"), "→");
+
+        // Password: everything passes through
+        e.set_context("com.apple.Safari", InputPurpose::Password);
+        assert_eq!(type_word(&mut e, "vieetj"), "vieetj");
+        e.clear_all();
+        assert_eq!(type_word(&mut e, "->"), "->");
+    }
+
+    #[test]
+    fn test_shortcut_template_cursor() {
+        let mut e = Engine::new();
+        e.shortcuts_mut()
+            .add(Shortcut::new("ht", "<p>{cursor}</p>"));
+
+        e.on_key_edit(keys::H, false, false, false);
+        e.on_key_edit(keys::T, false, false, false);
+        let edit = e.on_key_edit(keys::SPACE, false, false, false);
+        assert_eq!(edit.text, "<p></p> ");
+        assert_eq!(edit.cursor, Some(3));
+
+        // Legacy result has no caret info; the text is still inserted
+        e.clear();
+        e.on_key(keys::H, false, false);
+        e.on_key(keys::T, false, false);
+        let r = e.on_key(keys::SPACE, false, false);
+        assert_eq!(result_text(&r), "<p></p> ");
+    }
+
+    #[test]
+    fn test_english_words() {
+        let restored = |e: &mut Engine, input: &str| {
+            e.clear_all();
+            type_word(e, input)
+        };
+        let mut e = Engine::new();
+        e.set_english_auto_restore(true);
+        assert_eq!(restored(&mut e, "mix "), "mĩ ");
+        assert_eq!(restored(&mut e, "sax "), "sax ");
+
+        e.add_english_word("Mix");
+        e.add_english_word("mix");
+        assert_eq!(e.english_words(), ["mix"]);
+        assert_eq!(restored(&mut e, "mix "), "mix ");
+        assert_eq!(restored(&mut e, "Mix,"), "Mix,");
+        assert!(e.remove_english_word("MIX"));
+        assert!(!e.remove_english_word("mix"));
+        assert_eq!(restored(&mut e, "mix "), "mĩ ");
+
+        // Vietnamese words stay unless the raw word is common English
+        assert_eq!(restored(&mut e, "giso "), "giso ");
+        assert_eq!(restored(&mut e, "ddufi "), "ddufi ");
+        e.set_english_dictionary(true);
+        assert_eq!(restored(&mut e, "sax "), "sã ");
+        assert_eq!(restored(&mut e, "giso "), "gió ");
+        assert_eq!(restored(&mut e, "ddufi "), "đùi ");
+        assert_eq!(restored(&mut e, "fox "), "fox ");
+        assert_eq!(restored(&mut e, "afar "), "afar ");
+        // Only heuristic restores are checked: these read as Vietnamese
+        assert_eq!(restored(&mut e, "mix "), "mĩ ");
+        assert_eq!(restored(&mut e, "tets "), "tét ");
+        e.add_english_word("sax");
+        assert_eq!(restored(&mut e, "sax "), "sax ");
+    }
+
+    #[test]
+    fn test_strict_syllables() {
+        let typed = |e: &mut Engine, input: &str| {
+            e.clear_all();
+            type_word(e, input)
+        };
+        let mut e = Engine::new();
+        assert_eq!(typed(&mut e, "quuyns"), "quuýn");
+
+        e.set_strict_syllables(true);
+        assert_eq!(typed(&mut e, "quuyns"), "quuyns");
+        assert_eq!(typed(&mut e, "tefst"), "tét");
+        assert_eq!(typed(&mut e, "vieetj"), "việt");
+        assert_eq!(typed(&mut e, "nghieeng"), "nghiêng");
+        assert_eq!(typed(&mut e, "dduwowcj"), "được");
+        assert_eq!(typed(&mut e, "thuowr"), "thuở");
+        assert_eq!(typed(&mut e, "ngueechj"), "nguệch");
+        // Undo still steps through the word
+        let screen = typed(&mut e, "vieetj");
+        assert_eq!(screen, "việt");
+        assert_eq!(e.undo_len(), 2);
+
+        // Auto-restore gives up on transformed non-syllables
+        e.set_english_auto_restore(true);
+        assert_eq!(typed(&mut e, "ddaa "), "ddaa ");
+        assert_eq!(typed(&mut e, "hoaf "), "hoà ");
+        assert_eq!(typed(&mut e, "buwowu "), "bươu ");
+        assert_eq!(typed(&mut e, "nghiax "), "nghĩa ");
+    }
 }
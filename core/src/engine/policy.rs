@@ -0,0 +1,322 @@
+//! Per-app policy
+//!
+//! Remembers options per application so every host switches the same way
+//! on focus changes. Sections are app-ID globs (`*`, `?`, case-insensitive)
+//! holding `EngineConfig` keys that override the user's global options:
+//!
+//! ```text
+//! # Gõ Nhanh app policy
+//! version = 1
+//!
+//! [*]                         # default for every app
+//! auto_capitalize = true
+//!
+//! [com.apple.Terminal]
+//! enabled = false
+//!
+//! [com.microsoft.*]
+//! method = "vni"
+//! free_tone = true
+//! ```
+//!
+//! - All matching sections apply in file order, so later sections win
+//! - Apps without a matching section use the global options unchanged
+
+use super::config::{EngineConfig, VERSION};
+use super::Engine;
+use crate::lines::{self, LineError};
+use crate::utils::glob_match;
+use std::fmt;
+
+/// Option overrides for apps matching `pattern`
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppRule {
+    /// App-ID glob (e.g., "com.apple.mail", "org.mozilla.*")
+    pub pattern: String,
+    /// Validated (key, value) pairs in `EngineConfig::set` form
+    settings: Vec<(String, String)>,
+}
+
+impl AppRule {
+    /// Overridden options
+    pub fn settings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.settings.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// App-ID → options store (see module docs)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AppPolicy {
+    rules: Vec<AppRule>,
+}
+
+impl AppPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rules in the order they apply
+    pub fn rules(&self) -> &[AppRule] {
+        &self.rules
+    }
+
+    /// Override one option for `pattern`, adding the rule at the end if new
+    ///
+    /// Used to remember a choice made in an app, e.g.
+    /// `set("com.apple.Terminal", "enabled", "false")`.
+    pub fn set(&mut self, pattern: &str, key: &str, value: &str) -> Result<(), &'static str> {
+        if pattern.is_empty() {
+            return Err("empty app pattern");
+        }
+        let value = validate(key, value)?;
+        let rule = match self.rules.iter().position(|r| r.pattern == pattern) {
+            Some(i) => &mut self.rules[i],
+            None => {
+                self.rules.push(AppRule {
+                    pattern: pattern.to_string(),
+                    settings: vec![],
+                });
+                self.rules.last_mut().unwrap()
+            }
+        };
+        match rule.settings.iter_mut().find(|(k, _)| k == key) {
+            Some(setting) => setting.1 = value,
+            None => rule.settings.push((key.to_string(), value)),
+        }
+        Ok(())
+    }
+
+    /// Remove the rule for `pattern` (exact pattern, not a match)
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let len = self.rules.len();
+        self.rules.retain(|r| r.pattern != pattern);
+        self.rules.len() != len
+    }
+
+    /// Options for `app_id`: `base` with all matching rules applied
+    pub fn resolve(&self, app_id: &str, base: &EngineConfig) -> EngineConfig {
+        let mut config = *base;
+        for rule in self.rules.iter().filter(|r| glob_match(&r.pattern, app_id)) {
+            for (key, value) in &rule.settings {
+                // Validated when stored
+                let _ = config.set(key, value);
+            }
+        }
+        config
+    }
+
+    /// Parse a policy file (see module docs for the format)
+    pub fn parse(text: &str) -> Result<Self, LineError> {
+        let mut policy = Self::new();
+        let mut section: Option<String> = None;
+
+        for (line, content) in lines::content_lines(text) {
+            let err = |reason| LineError { line, reason };
+            if let Some(pattern) = content.strip_prefix('[') {
+                let pattern = pattern.strip_suffix(']').ok_or(err("expected ]"))?.trim();
+                if pattern.is_empty() {
+                    return Err(err("empty app pattern"));
+                }
+                // Keep sections without keys so they round-trip
+                if !policy.rules.iter().any(|r| r.pattern == pattern) {
+                    policy.rules.push(AppRule {
+                        pattern: pattern.to_string(),
+                        settings: vec![],
+                    });
+                }
+                section = Some(pattern.to_string());
+                continue;
+            }
+
+            let (key, value) = lines::key_value(content).map_err(err)?;
+            match &section {
+                None if key == "version" => lines::check_version(value, VERSION).map_err(err)?,
+                None => return Err(err("expected [app]")),
+                Some(pattern) => policy.set(pattern, key, value).map_err(err)?,
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// Validate a value for `key` and normalize it for storage
+fn validate(key: &str, value: &str) -> Result<String, &'static str> {
+    let value = lines::unquote(value);
+    EngineConfig::default().set(key, value)?;
+    Ok(value.to_string())
+}
+
+impl fmt::Display for AppPolicy {
+    /// Serialize in the format read by `parse`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version = {}", VERSION)?;
+        for rule in &self.rules {
+            writeln!(f, "\n[{}]", rule.pattern)?;
+            for (key, value) in &rule.settings {
+                match value.as_str() {
+                    "true" | "false" => writeln!(f, "{} = {}", key, value)?,
+                    _ => writeln!(f, "{} = \"{}\"", key, value)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Engine {
+    /// Per-app policy applied by `set_context`
+    pub fn policy(&self) -> &AppPolicy {
+        &self.policy
+    }
+
+    /// Mutable access to the per-app policy (takes effect at the next focus change)
+    pub fn policy_mut(&mut self) -> &mut AppPolicy {
+        &mut self.policy
+    }
+
+    /// Replace the policy and apply it to the focused app
+    pub fn set_policy(&mut self, policy: AppPolicy) {
+        self.policy = policy;
+        let app_id = self.shortcuts.app_id().to_string();
+        self.apply_policy(&app_id);
+    }
+
+    /// User options without the focused app's overrides (what hosts persist)
+    pub fn global_config(&self) -> EngineConfig {
+        self.policy_base.unwrap_or_else(|| self.config())
+    }
+
+    /// Replace the user options, keeping the focused app's overrides on top
+    pub fn set_global_config(&mut self, config: &EngineConfig) {
+        self.policy_base = None;
+        self.apply_config(config);
+        let app_id = self.shortcuts.app_id().to_string();
+        self.apply_policy(&app_id);
+    }
+
+    /// Switch options for the newly focused app
+    ///
+    /// The global options are kept aside while an app's overrides are active
+    /// and restored when focus moves to an app without rules. Setters called
+    /// meanwhile only last until the next switch; store them with
+    /// `AppPolicy::set` to keep them for the app.
+    pub(super) fn apply_policy(&mut self, app_id: &str) {
+        let base = self.policy_base.take().unwrap_or_else(|| self.config());
+        let config = self.policy.resolve(app_id, &base);
+        if config != base {
+            self.policy_base = Some(base);
+        }
+        if config != self.config() {
+            self.apply_config(&config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::shortcut::InputPurpose;
+
+    const POLICY: &str = "# shared\n\
+                          version = 1\n\
+                          \n\
+                          [*]\n\
+                          auto_capitalize = true\n\
+                          \n\
+                          [com.apple.Terminal]\n\
+                          enabled = false\n\
+                          \n\
+                          [com.microsoft.*]   # Office\n\
+                          method = vni\n\
+                          free_tone = true\n";
+
+    #[test]
+    fn test_resolve() {
+        let policy = AppPolicy::parse(POLICY).unwrap();
+        let base = EngineConfig::default();
+
+        let word = policy.resolve("com.microsoft.Word", &base);
+        assert_eq!(word.method, 1);
+        assert!(word.free_tone && word.auto_capitalize && word.enabled);
+
+        let terminal = policy.resolve("com.apple.terminal", &base);
+        assert!(!terminal.enabled);
+        assert_eq!(terminal.method, 0);
+
+        let other = policy.resolve("", &base);
+        assert_eq!(
+            other,
+            EngineConfig {
+                auto_capitalize: true,
+                ..base
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_and_roundtrip() {
+        let mut policy = AppPolicy::parse(POLICY).unwrap();
+        policy.set("com.apple.Terminal", "enabled", "true").unwrap();
+        policy
+            .set("com.apple.mail", "output_charset", "tcvn3")
+            .unwrap();
+        assert_eq!(policy.set("x", "color", "red"), Err("unknown key"));
+        assert_eq!(
+            policy.set("x", "enabled", "yes"),
+            Err("expected true or false")
+        );
+        assert_eq!(policy.rules().len(), 4);
+
+        let text = policy.to_string();
+        assert!(text.contains("[com.microsoft.*]\nmethod = \"vni\"\nfree_tone = true\n"));
+        assert!(text.ends_with("[com.apple.mail]\noutput_charset = \"tcvn3\"\n"));
+        assert_eq!(AppPolicy::parse(&text), Ok(policy.clone()));
+
+        assert!(policy.remove("com.apple.mail"));
+        assert!(!policy.remove("com.apple.mail"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |text: &str| {
+            let e = AppPolicy::parse(text).unwrap_err();
+            (e.line, e.reason)
+        };
+        assert_eq!(err("enabled = false"), (1, "expected [app]"));
+        assert_eq!(err("[a]\nenabled = no"), (2, "expected true or false"));
+        assert_eq!(err("[com.apple"), (1, "expected ]"));
+        assert_eq!(err("[ ]"), (1, "empty app pattern"));
+        assert_eq!(err("version = 2"), (1, "unsupported version"));
+    }
+
+    #[test]
+    fn test_engine_focus_switch() {
+        let mut e = Engine::new();
+        *e.policy_mut() = AppPolicy::parse(POLICY).unwrap();
+
+        e.set_context("com.microsoft.Word", InputPurpose::Text);
+        assert_eq!(e.config().method, 1);
+        e.set_context("com.apple.Terminal", InputPurpose::Text);
+        assert!(!e.config().enabled);
+        assert_eq!(e.config().method, 0);
+
+        // Global options change underneath the override
+        let vni = EngineConfig {
+            method: 1,
+            ..Default::default()
+        };
+        e.set_global_config(&vni);
+        assert!(!e.config().enabled);
+        assert_eq!(e.global_config().method, 1);
+        e.set_global_config(&EngineConfig::default());
+
+        // Leaving a ruled app restores the global options
+        e.policy_mut().remove("*");
+        e.set_context("org.gnome.Gedit", InputPurpose::Text);
+        assert_eq!(e.config(), EngineConfig::default());
+    }
+}
@@ -0,0 +1,308 @@
+//! Key log recording and replay
+//!
+//! While recording, the engine logs every call a host makes, one per line,
+//! so a bug report can carry the exact session and be re-run as a test:
+//!
+//! ```text
+//! #gonhanh-keylog version=1
+//! o method = "vni"              # option changed since the previous event
+//! k 14 -                        # key 14, no modifiers, Action::None
+//! k 14 c 1 1 0 "Ê"              # caps: action, backspace, flags, text
+//! k 49 s                        # modifiers: c=caps ^=ctrl s=shift
+//! u 1 2 0 "ee"                  # undo and its result
+//! a 0 1 4 0 "tôi "              # suggestion 0 accepted
+//! c                             # clear (C: clear all)
+//! ```
+//!
+//! - Options are written as `EngineConfig` lines, compared to the defaults
+//!   for the first event and to the previous event after that
+//! - Shortcuts, word lists, app context and state restores are not logged:
+//!   set them up on the replaying engine first
+
+use super::config::EngineConfig;
+use super::{Action, Engine, Result};
+use crate::lines::{self, LineError};
+use std::fmt;
+
+/// Header line of a key log (followed by the version)
+const HEADER: &str = "#gonhanh-keylog version=";
+
+/// Current key log version
+pub const LOG_VERSION: u32 = 1;
+
+/// Log being written by a recording engine
+pub(super) struct Recorder {
+    log: String,
+    /// Options as of the last logged event
+    config: EngineConfig,
+}
+
+/// Event whose result differs from the log
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// 1-based line number
+    pub line: usize,
+    /// Result as logged ("" for `Action::None`)
+    pub expected: String,
+    /// Result of the replay
+    pub actual: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: expected [{}], got [{}]",
+            self.line, self.expected, self.actual
+        )
+    }
+}
+
+impl Engine {
+    /// Start a new key log, dropping any current one
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(Recorder {
+            log: format!("{}{}\n", HEADER, LOG_VERSION),
+            config: EngineConfig::default(),
+        });
+    }
+
+    /// Stop recording, returning the log
+    pub fn stop_recording(&mut self) -> Option<String> {
+        self.recorder.take().map(|r| r.log)
+    }
+
+    /// Log so far (None when not recording)
+    pub fn recording(&self) -> Option<&str> {
+        self.recorder.as_ref().map(|r| r.log.as_str())
+    }
+
+    /// Log a key and its result
+    pub(super) fn record_key(&mut self, key: u16, caps: bool, ctrl: bool, shift: bool, r: &Result) {
+        if self.recorder.is_none() {
+            return;
+        }
+        let mut mods = String::new();
+        for (on, flag) in [(caps, 'c'), (ctrl, '^'), (shift, 's')] {
+            if on {
+                mods.push(flag);
+            }
+        }
+        if mods.is_empty() {
+            mods.push('-');
+        }
+        self.record(&format!("k {} {}", key, mods), r);
+    }
+
+    /// Log an undo and its result
+    pub(super) fn record_undo(&mut self, r: &Result) {
+        if self.recorder.is_some() {
+            self.record("u", r);
+        }
+    }
+
+    /// Log an accepted suggestion and its result
+    pub(super) fn record_accept(&mut self, index: usize, r: &Result) {
+        if self.recorder.is_some() {
+            self.record(&format!("a {}", index), r);
+        }
+    }
+
+    /// Log a clear requested by the host (not the engine's own word ends)
+    pub(super) fn record_clear(&mut self, all: bool) {
+        self.record_event(if all { "C" } else { "c" });
+    }
+
+    fn record(&mut self, event: &str, r: &Result) {
+        match result_text(r) {
+            text if text.is_empty() => self.record_event(event),
+            text => self.record_event(&format!("{} {}", event, text)),
+        }
+    }
+
+    /// Append an event line, preceded by the options changed since the last one
+    fn record_event(&mut self, line: &str) {
+        if self.recorder.is_none() {
+            return;
+        }
+        let current = self.config();
+        let Some(recorder) = self.recorder.as_mut() else {
+            return;
+        };
+        if current != recorder.config {
+            let old = recorder.config.to_string();
+            for option in current.to_string().lines().skip(1) {
+                if !old.lines().any(|l| l == option) {
+                    recorder.log.push_str("o ");
+                    recorder.log.push_str(option);
+                    recorder.log.push('\n');
+                }
+            }
+            recorder.config = current;
+        }
+        recorder.log.push_str(line);
+        recorder.log.push('\n');
+    }
+
+    /// Re-run a key log, returning the events whose result changed
+    ///
+    /// Options start from the defaults; the rest of the engine (shortcuts,
+    /// word lists) is used as is. The buffer and history are cleared first.
+    pub fn replay(&mut self, log: &str) -> std::result::Result<Vec<Mismatch>, LineError> {
+        let mut config = EngineConfig::default();
+        self.apply_config(&config);
+        self.reset_all();
+        let mut mismatches = vec![];
+
+        for (idx, line) in log.lines().enumerate() {
+            let err = |reason| LineError {
+                line: idx + 1,
+                reason,
+            };
+            let line = line.trim();
+            if let Some(version) = line.strip_prefix(HEADER) {
+                lines::check_version(version, LOG_VERSION).map_err(err)?;
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (event, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let (r, expected) = match event {
+                "o" => {
+                    let (key, value) = lines::key_value(rest).map_err(err)?;
+                    config.set(key, value).map_err(err)?;
+                    self.apply_config(&config);
+                    continue;
+                }
+                "c" | "C" if rest.is_empty() => {
+                    if event == "c" {
+                        self.reset();
+                    } else {
+                        self.reset_all();
+                    }
+                    continue;
+                }
+                "k" => {
+                    let mut fields = rest.splitn(3, ' ');
+                    let key = fields.next().and_then(|k| k.parse().ok());
+                    let key = key.ok_or(err("invalid key"))?;
+                    let mods = fields.next().ok_or(err("expected modifiers"))?;
+                    if mods != "-" && !mods.chars().all(|c| matches!(c, 'c' | '^' | 's')) {
+                        return Err(err("invalid modifiers"));
+                    }
+                    let (caps, ctrl, shift) =
+                        (mods.contains('c'), mods.contains('^'), mods.contains('s'));
+                    (self.on_key_ext(key, caps, ctrl, shift), fields.next())
+                }
+                "u" => (self.undo(), Some(rest)),
+                "a" => {
+                    let (index, expected) = rest.split_once(' ').unwrap_or((rest, ""));
+                    let index = index.parse().map_err(|_| err("invalid index"))?;
+                    (self.accept_suggestion(index), Some(expected))
+                }
+                _ => return Err(err("unknown event")),
+            };
+
+            let expected = expected.unwrap_or("").trim();
+            let actual = result_text(&r);
+            if actual != expected {
+                mismatches.push(Mismatch {
+                    line: idx + 1,
+                    expected: expected.to_string(),
+                    actual,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+/// Logged form of a result: empty for `Action::None`
+fn result_text(r: &Result) -> String {
+    if r.action == Action::None as u8 {
+        return String::new();
+    }
+    let text: String = r.chars[..r.count as usize]
+        .iter()
+        .filter_map(|&c| char::from_u32(c))
+        .collect();
+    format!("{} {} {} {:?}", r.action, r.backspace, r.flags, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+    use crate::engine::shortcut::Shortcut;
+    use crate::utils::type_word;
+
+    #[test]
+    fn test_record() {
+        let mut e = Engine::new();
+        e.start_recording();
+        e.set_method(1);
+        type_word(&mut e, "a6");
+        e.set_method(0);
+        e.clear_all();
+        e.on_key_ext(keys::A, true, false, false);
+        e.undo();
+
+        let log = e.stop_recording().unwrap();
+        assert_eq!(
+            log,
+            "#gonhanh-keylog version=1\n\
+             o method = \"vni\"\n\
+             k 0 -\n\
+             k 22 - 1 1 0 \"â\"\n\
+             o method = \"telex\"\n\
+             C\n\
+             k 0 c\n\
+             u\n"
+        );
+        assert_eq!(e.recording(), None);
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut e = Engine::new();
+        e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+        e.set_free_tone(true);
+        e.start_recording();
+        type_word(&mut e, "vieetj vn Ddi<");
+        e.undo();
+        let log = e.stop_recording().unwrap();
+        assert!(log.starts_with("#gonhanh-keylog version=1\no free_tone = true\n"));
+
+        // Same engine setup: same results
+        let mut replayer = Engine::new();
+        replayer
+            .shortcuts_mut()
+            .add(Shortcut::new("vn", "Việt Nam"));
+        assert_eq!(replayer.replay(&log), Ok(vec![]));
+
+        // Without the shortcut, the space after "vn" is typed through
+        let mismatches = Engine::new().replay(&log).unwrap();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].actual, "");
+        assert_eq!(mismatches[0].expected, "1 2 0 \"Việt Nam \"");
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let err = |log: &str| Engine::new().replay(log).unwrap_err();
+        assert_eq!(
+            err("#gonhanh-keylog version=2"),
+            LineError {
+                line: 1,
+                reason: "unsupported version"
+            }
+        );
+        assert_eq!(err("\nk x -").reason, "invalid key");
+        assert_eq!(err("k 0 q").reason, "invalid modifiers");
+        assert_eq!(err("o colour = red").reason, "unknown key");
+        assert_eq!(err("a x").reason, "invalid index");
+        assert_eq!(err("z").reason, "unknown event");
+    }
+}
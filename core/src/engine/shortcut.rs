@@ -2,14 +2,35 @@
 //!
 //! Allows users to define shortcuts like "vn" → "Việt Nam"
 //! Shortcuts can be specific to input methods (Telex/VNI) or apply to all.
+//!
+//! ## File format
+//!
+//! One shortcut per line, TAB-separated, with optional comma-separated flags:
+//!
+//! ```text
+//! #gonhanh-shortcuts version=1
+//! vn<TAB>Việt Nam
+//! -><TAB>→<TAB>immediate
+//! VN<TAB>Việt Nam<TAB>match-case,telex,disabled
+//! sig<TAB>Trân trọng,\nNam<TAB>app=com.apple.mail
+//! ```
+//!
+//! - Flags: `immediate`, `match-case`, `telex` or `vni`, `disabled`,
+//!   `app=<glob>` and `purpose=<name>` (see `Shortcut::applies_in`)
+//! - Escapes in trigger/replacement: `\\`, `\t`, `\n`, and `\#` for a leading '#';
+//!   `\,` in flag values
+//! - A trigger may have one shortcut per scope (method, app, purpose); the
+//!   most specific one that applies is used
+//! - Files without the header are read as UniKey macros: `trigger:replacement`
+//!   lines, ';' comments
+//!
+//! Replacements may contain placeholders (`{date}`, `{cursor}`, ...), see `template`.
 
-use super::buffer::MAX;
+use super::template::{self, Context};
+use crate::lines::{self, LineError};
+use crate::utils::glob_match;
 use std::collections::HashMap;
-
-/// Maximum replacement length in UTF-32 codepoints (matches Result.chars array size)
-/// This limit ensures replacement fits in the FFI result buffer.
-/// Note: Vietnamese characters with diacritics (ồ, ế, ẫ) count as 1 codepoint each.
-pub const MAX_REPLACEMENT_LEN: usize = MAX - 1; // -1 to leave room for trailing space
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Input method that shortcut applies to
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
@@ -23,6 +44,54 @@ pub enum InputMethod {
     Vni,
 }
 
+/// Kind of text field being typed into, reported by the host
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum InputPurpose {
+    /// Regular text (also used when the host doesn't know)
+    #[default]
+    Text,
+    Terminal,
+    Password,
+    Url,
+    Code,
+}
+
+impl InputPurpose {
+    /// FFI id: 0=text, 1=terminal, 2=password, 3=url, 4=code
+    pub fn from_u8(id: u8) -> Self {
+        match id {
+            1 => Self::Terminal,
+            2 => Self::Password,
+            3 => Self::Url,
+            4 => Self::Code,
+            _ => Self::Text,
+        }
+    }
+
+    /// Name used in shortcut files
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Terminal => "terminal",
+            Self::Password => "password",
+            Self::Url => "url",
+            Self::Code => "code",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::Text,
+            Self::Terminal,
+            Self::Password,
+            Self::Url,
+            Self::Code,
+        ]
+        .into_iter()
+        .find(|p| p.name() == name)
+    }
+}
+
 /// Trigger condition for shortcut
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TriggerCondition {
@@ -56,71 +125,77 @@ pub struct Shortcut {
     pub enabled: bool,
     /// Which input method this shortcut applies to
     pub input_method: InputMethod,
+    /// App IDs this shortcut applies to (glob with `*` and `?`), None = any app
+    pub app_scope: Option<String>,
+    /// Field type this shortcut applies to, None = any
+    pub purpose_scope: Option<InputPurpose>,
 }
 
 impl Shortcut {
-    /// Validate and truncate replacement if it exceeds MAX_REPLACEMENT_LEN.
-    /// Counts UTF-32 codepoints (Vietnamese diacritics = 1 codepoint each).
-    fn validate_replacement(replacement: &str) -> String {
-        let char_count = replacement.chars().count();
-        if char_count <= MAX_REPLACEMENT_LEN {
-            replacement.to_string()
-        } else {
-            // Truncate to MAX_REPLACEMENT_LEN codepoints
-            replacement.chars().take(MAX_REPLACEMENT_LEN).collect()
-        }
-    }
-
     /// Create a new shortcut with word boundary trigger (applies to all input methods)
     /// Trigger must match exactly (case-sensitive), output is exactly as defined.
-    /// Replacement is truncated to MAX_REPLACEMENT_LEN (63) codepoints if too long.
     pub fn new(trigger: &str, replacement: &str) -> Self {
         Self {
             trigger: trigger.to_string(), // Keep original case
-            replacement: Self::validate_replacement(replacement),
+            replacement: replacement.to_string(),
             condition: TriggerCondition::OnWordBoundary,
             case_mode: CaseMode::Exact, // Exact match, no case transformation
             enabled: true,
             input_method: InputMethod::All,
+            app_scope: None,
+            purpose_scope: None,
         }
     }
 
     /// Create an immediate trigger shortcut (applies to all input methods).
-    /// Replacement is truncated to MAX_REPLACEMENT_LEN (63) codepoints if too long.
     pub fn immediate(trigger: &str, replacement: &str) -> Self {
         Self {
             trigger: trigger.to_string(), // Keep original case
-            replacement: Self::validate_replacement(replacement),
+            replacement: replacement.to_string(),
             condition: TriggerCondition::Immediate,
             case_mode: CaseMode::Exact,
             enabled: true,
             input_method: InputMethod::All,
+            app_scope: None,
+            purpose_scope: None,
         }
     }
 
     /// Create a Telex-specific shortcut with immediate trigger.
-    /// Replacement is truncated to MAX_REPLACEMENT_LEN (63) codepoints if too long.
     pub fn telex(trigger: &str, replacement: &str) -> Self {
         Self {
             trigger: trigger.to_string(), // Keep original case
-            replacement: Self::validate_replacement(replacement),
+            replacement: replacement.to_string(),
             condition: TriggerCondition::Immediate,
             case_mode: CaseMode::Exact,
             enabled: true,
             input_method: InputMethod::Telex,
+            app_scope: None,
+            purpose_scope: None,
         }
     }
 
     /// Create a VNI-specific shortcut with immediate trigger.
-    /// Replacement is truncated to MAX_REPLACEMENT_LEN (63) codepoints if too long.
     pub fn vni(trigger: &str, replacement: &str) -> Self {
         Self {
             trigger: trigger.to_string(), // Keep original case
-            replacement: Self::validate_replacement(replacement),
+            replacement: replacement.to_string(),
             condition: TriggerCondition::Immediate,
             case_mode: CaseMode::Exact,
             enabled: true,
             input_method: InputMethod::Vni,
+            app_scope: None,
+            purpose_scope: None,
+        }
+    }
+
+    /// Create a shortcut, picking the trigger type from its characters:
+    /// symbol-only triggers ("->", "=>") fire immediately, others on word boundary.
+    pub fn auto(trigger: &str, replacement: &str) -> Self {
+        if trigger.chars().all(|c| !c.is_alphabetic()) {
+            Self::immediate(trigger, replacement)
+        } else {
+            Self::new(trigger, replacement)
         }
     }
 
@@ -130,6 +205,47 @@ impl Shortcut {
         self
     }
 
+    /// Restrict this shortcut to apps matching `glob` (e.g., "com.apple.mail", "*.mail*")
+    pub fn in_app(mut self, glob: &str) -> Self {
+        self.app_scope = Some(glob.to_string());
+        self
+    }
+
+    /// Restrict this shortcut to one kind of field
+    pub fn for_purpose(mut self, purpose: InputPurpose) -> Self {
+        self.purpose_scope = Some(purpose);
+        self
+    }
+
+    /// Check if shortcut applies in the given context
+    ///
+    /// App globs match case-insensitively; an unknown app (empty ID) only
+    /// matches unscoped shortcuts.
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn applies_in(&self, app_id: &str, purpose: InputPurpose) -> bool {
+        let app_ok = self
+            .app_scope
+            .as_deref()
+            .map_or(true, |glob| !app_id.is_empty() && glob_match(glob, app_id));
+        app_ok && self.purpose_scope.map_or(true, |p| p == purpose)
+    }
+
+    /// Same method, app and purpose scope (adding one replaces the other)
+    fn same_scope(&self, other: &Shortcut) -> bool {
+        self.input_method == other.input_method
+            && self.app_scope == other.app_scope
+            && self.purpose_scope == other.purpose_scope
+    }
+
+    /// Rank among shortcuts of one trigger that apply: higher wins
+    fn specificity(&self) -> (bool, bool, bool) {
+        (
+            self.app_scope.is_some(),
+            self.purpose_scope.is_some(),
+            self.input_method != InputMethod::All,
+        )
+    }
+
     /// Check if shortcut applies to given input method
     ///
     /// - If shortcut is for `All`: matches any method
@@ -159,22 +275,36 @@ pub struct ShortcutMatch {
     pub output: String,
     /// Whether to include the trigger key in output
     pub include_trigger_key: bool,
+    /// Chars to move the caret back after inserting `output` (`{cursor}`), 0 = stay at end
+    pub cursor_back: usize,
 }
 
 /// Shortcut table manager
 #[derive(Debug, Default)]
 pub struct ShortcutTable {
-    /// Shortcuts indexed by trigger (lowercase)
-    shortcuts: HashMap<String, Shortcut>,
-    /// Sorted triggers by length (longest first) for matching
-    sorted_triggers: Vec<String>,
+    /// Shortcuts indexed by trigger, one per scope
+    shortcuts: HashMap<String, Vec<Shortcut>>,
+    /// Local time offset from UTC in minutes (for `{date}`/`{time}`)
+    utc_offset_minutes: i32,
+    /// Host-provided text for `{clipboard}`
+    clipboard: String,
+    /// Focused app ID (e.g., bundle ID), empty if unknown
+    app_id: String,
+    /// Focused field type
+    purpose: InputPurpose,
+    /// Expansions so far per trigger (for `{counter}`)
+    counters: HashMap<String, u32>,
 }
 
 impl ShortcutTable {
     pub fn new() -> Self {
         Self {
             shortcuts: HashMap::new(),
-            sorted_triggers: vec![],
+            utc_offset_minutes: 0,
+            clipboard: String::new(),
+            app_id: String::new(),
+            purpose: InputPurpose::Text,
+            counters: HashMap::new(),
         }
     }
 
@@ -224,20 +354,20 @@ impl ShortcutTable {
         table
     }
 
-    /// Add a shortcut
+    /// Add a shortcut, replacing the one with the same trigger and scope
     pub fn add(&mut self, shortcut: Shortcut) {
-        let trigger = shortcut.trigger.clone();
-        self.shortcuts.insert(trigger.clone(), shortcut);
-        self.rebuild_sorted_triggers();
+        let variants = self.shortcuts.entry(shortcut.trigger.clone()).or_default();
+        match variants.iter_mut().find(|s| s.same_scope(&shortcut)) {
+            Some(existing) => *existing = shortcut,
+            None => variants.push(shortcut),
+        }
     }
 
-    /// Remove a shortcut (exact match, case-sensitive)
-    pub fn remove(&mut self, trigger: &str) -> Option<Shortcut> {
-        let result = self.shortcuts.remove(trigger);
-        if result.is_some() {
-            self.rebuild_sorted_triggers();
-        }
-        result
+    /// Remove all shortcuts of a trigger (exact match, case-sensitive)
+    pub fn remove(&mut self, trigger: &str) -> Vec<Shortcut> {
+        let removed = self.shortcuts.remove(trigger).unwrap_or_default();
+        self.counters.remove(trigger);
+        removed
     }
 
     /// Check if buffer matches any shortcut (for any input method)
@@ -255,17 +385,15 @@ impl ShortcutTable {
         buffer: &str,
         method: InputMethod,
     ) -> Option<(&str, &Shortcut)> {
-        // Longest-match-first, exact case-sensitive match
-        for trigger in &self.sorted_triggers {
-            if buffer == *trigger {
-                if let Some(shortcut) = self.shortcuts.get(trigger) {
-                    if shortcut.enabled && shortcut.applies_to(method) {
-                        return Some((trigger, shortcut));
-                    }
-                }
-            }
-        }
-        None
+        // Exact case-sensitive match, most specific scope first
+        let (trigger, variants) = self.shortcuts.get_key_value(buffer)?;
+        variants
+            .iter()
+            .filter(|s| {
+                s.enabled && s.applies_to(method) && s.applies_in(&self.app_id, self.purpose)
+            })
+            .max_by_key(|s| s.specificity())
+            .map(|s| (trigger.as_str(), s))
     }
 
     /// Try to match buffer with trigger key (for any input method)
@@ -278,7 +406,7 @@ impl ShortcutTable {
     /// # Returns
     /// ShortcutMatch if a shortcut should be triggered
     pub fn try_match(
-        &self,
+        &mut self,
         buffer: &str,
         key_char: Option<char>,
         is_word_boundary: bool,
@@ -297,43 +425,89 @@ impl ShortcutTable {
     /// # Returns
     /// ShortcutMatch if a shortcut should be triggered
     pub fn try_match_for_method(
-        &self,
+        &mut self,
         buffer: &str,
         key_char: Option<char>,
         is_word_boundary: bool,
         method: InputMethod,
     ) -> Option<ShortcutMatch> {
         let (trigger, shortcut) = self.lookup_for_method(buffer, method)?;
-
-        match shortcut.condition {
-            TriggerCondition::Immediate => {
-                let output = self.apply_case(buffer, &shortcut.replacement, shortcut.case_mode);
-                Some(ShortcutMatch {
-                    // Use char count, not byte length (UTF-8 chars like đ are multi-byte)
-                    backspace_count: trigger.chars().count(),
-                    output,
-                    include_trigger_key: false,
-                })
-            }
-            TriggerCondition::OnWordBoundary => {
-                if is_word_boundary {
-                    let mut output =
-                        self.apply_case(buffer, &shortcut.replacement, shortcut.case_mode);
-                    // Append the trigger key (space, etc.)
-                    if let Some(ch) = key_char {
-                        output.push(ch);
-                    }
-                    Some(ShortcutMatch {
-                        // Use char count, not byte length (UTF-8 chars like đ are multi-byte)
-                        backspace_count: trigger.chars().count(),
-                        output,
-                        include_trigger_key: true,
-                    })
-                } else {
-                    None
-                }
+        let include_trigger_key = match shortcut.condition {
+            TriggerCondition::Immediate => false,
+            TriggerCondition::OnWordBoundary if is_word_boundary => true,
+            TriggerCondition::OnWordBoundary => return None,
+        };
+        // Use char count, not byte length (UTF-8 chars like đ are multi-byte)
+        let backspace_count = trigger.chars().count();
+        let template = shortcut.replacement.clone();
+        let case_mode = shortcut.case_mode;
+
+        let (replacement, cursor) = self.evaluate(buffer, &template);
+        let mut output = self.apply_case(buffer, &replacement, case_mode);
+        if include_trigger_key {
+            // Append the trigger key (space, etc.)
+            if let Some(ch) = key_char {
+                output.push(ch);
             }
         }
+        let cursor_back = cursor.map_or(0, |c| output.chars().count().saturating_sub(c));
+
+        Some(ShortcutMatch {
+            backspace_count,
+            output,
+            include_trigger_key,
+            cursor_back,
+        })
+    }
+
+    /// Evaluate template placeholders of a matched shortcut
+    /// Returns the replacement and the `{cursor}` position in it
+    fn evaluate(&mut self, trigger: &str, replacement: &str) -> (String, Option<usize>) {
+        if !template::is_template(replacement) {
+            return (replacement.to_string(), None);
+        }
+        let mut counter = 0;
+        if replacement.contains("{counter}") {
+            let count = self.counters.entry(trigger.to_string()).or_default();
+            *count = count.saturating_add(1);
+            counter = *count;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        let ctx = Context {
+            local_time: now + self.utc_offset_minutes as i64 * 60,
+            counter,
+            clipboard: &self.clipboard,
+        };
+        let expansion = template::expand(replacement, &ctx);
+        (expansion.text, expansion.cursor)
+    }
+
+    /// Set the focused app and field type; scoped shortcuts only match there
+    pub fn set_context(&mut self, app_id: &str, purpose: InputPurpose) {
+        self.app_id = app_id.to_string();
+        self.purpose = purpose;
+    }
+
+    /// Focused app ID, empty if unknown
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    /// Focused field type
+    pub fn purpose(&self) -> InputPurpose {
+        self.purpose
+    }
+
+    /// Set the local time offset used by `{date}` and `{time}`
+    pub fn set_utc_offset_minutes(&mut self, minutes: i32) {
+        self.utc_offset_minutes = minutes;
+    }
+
+    /// Set the text inserted by `{clipboard}`
+    pub fn set_clipboard(&mut self, text: &str) {
+        self.clipboard = text.to_string();
     }
 
     /// Apply case transformation based on mode
@@ -364,13 +538,6 @@ impl ShortcutTable {
         }
     }
 
-    /// Rebuild sorted triggers list (longest first)
-    fn rebuild_sorted_triggers(&mut self) {
-        self.sorted_triggers = self.shortcuts.keys().cloned().collect();
-        self.sorted_triggers
-            .sort_by_key(|s| std::cmp::Reverse(s.len()));
-    }
-
     /// Check if shortcut table is empty
     pub fn is_empty(&self) -> bool {
         self.shortcuts.is_empty()
@@ -378,19 +545,390 @@ impl ShortcutTable {
 
     /// Get number of shortcuts
     pub fn len(&self) -> usize {
-        self.shortcuts.len()
+        self.shortcuts.values().map(Vec::len).sum()
     }
 
     /// Clear all shortcuts
     pub fn clear(&mut self) {
         self.shortcuts.clear();
-        self.sorted_triggers.clear();
+        self.counters.clear();
+    }
+
+    /// Iterate over shortcuts (arbitrary order)
+    pub fn iter(&self) -> impl Iterator<Item = &Shortcut> {
+        self.shortcuts.values().flatten()
+    }
+
+    /// Add shortcuts from a file (see module docs), returning how many
+    ///
+    /// Nothing is added if any line fails to parse.
+    pub fn import(&mut self, text: &str) -> Result<usize, LineError> {
+        let shortcuts = parse(text)?;
+        let count = shortcuts.len();
+        for shortcut in shortcuts {
+            self.add(shortcut);
+        }
+        Ok(count)
+    }
+
+    /// Serialize all shortcuts in the native file format, sorted by trigger
+    pub fn export(&self) -> String {
+        let mut shortcuts: Vec<&Shortcut> = self.iter().collect();
+        shortcuts.sort_by(|a, b| a.trigger.cmp(&b.trigger));
+
+        let mut out = format!("{}{}\n", HEADER, FILE_VERSION);
+        for s in shortcuts {
+            let mut flags = vec![];
+            if s.condition == TriggerCondition::Immediate {
+                flags.push("immediate".to_string());
+            }
+            if s.case_mode == CaseMode::MatchCase {
+                flags.push("match-case".to_string());
+            }
+            match s.input_method {
+                InputMethod::All => {}
+                InputMethod::Telex => flags.push("telex".to_string()),
+                InputMethod::Vni => flags.push("vni".to_string()),
+            }
+            if !s.enabled {
+                flags.push("disabled".to_string());
+            }
+            if let Some(glob) = &s.app_scope {
+                flags.push(format!("app={}", escape(glob).replace(',', "\\,")));
+            }
+            if let Some(purpose) = s.purpose_scope {
+                flags.push(format!("purpose={}", purpose.name()));
+            }
+
+            let mut trigger = escape(&s.trigger);
+            if trigger.starts_with('#') {
+                trigger.insert(0, '\\');
+            }
+            out.push_str(&trigger);
+            out.push('\t');
+            out.push_str(&escape(&s.replacement));
+            if !flags.is_empty() {
+                out.push('\t');
+                out.push_str(&flags.join(","));
+            }
+            out.push('\n');
+        }
+        out
     }
 }
 
+/// Header line of the native file format (followed by the version)
+const HEADER: &str = "#gonhanh-shortcuts version=";
+
+/// Current shortcut file version
+pub const FILE_VERSION: u32 = 1;
+
+/// Parse a shortcut file: native format if it starts with the header, else UniKey macros
+pub fn parse(text: &str) -> Result<Vec<Shortcut>, LineError> {
+    let native = text
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|l| l.trim_start().starts_with(HEADER));
+
+    let mut shortcuts = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        let err = |reason| LineError {
+            line: idx + 1,
+            reason,
+        };
+        let shortcut = if native {
+            if let Some(version) = line.trim().strip_prefix(HEADER) {
+                lines::check_version(version, FILE_VERSION).map_err(err)?;
+                continue;
+            }
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            parse_native_line(line).map_err(err)?
+        } else {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let (trigger, replacement) = line
+                .split_once(':')
+                .ok_or(err("expected trigger:replacement"))?;
+            let trigger = trigger.trim();
+            if trigger.is_empty() {
+                return Err(err("empty trigger"));
+            }
+            Shortcut::auto(trigger, replacement.trim())
+        };
+        shortcuts.push(shortcut);
+    }
+    Ok(shortcuts)
+}
+
+fn parse_native_line(line: &str) -> Result<Shortcut, &'static str> {
+    let mut fields = line.split('\t');
+    let trigger = unescape(fields.next().unwrap_or(""))?;
+    let replacement = unescape(fields.next().ok_or("expected trigger<TAB>replacement")?)?;
+    if trigger.is_empty() {
+        return Err("empty trigger");
+    }
+
+    let mut shortcut = Shortcut::new(&trigger, &replacement);
+    for flag in split_flags(fields.next().unwrap_or("")) {
+        match flag {
+            "" => {}
+            "immediate" => shortcut.condition = TriggerCondition::Immediate,
+            "match-case" => shortcut.case_mode = CaseMode::MatchCase,
+            "telex" => shortcut.input_method = InputMethod::Telex,
+            "vni" => shortcut.input_method = InputMethod::Vni,
+            "disabled" => shortcut.enabled = false,
+            _ => {
+                if let Some(glob) = flag.strip_prefix("app=") {
+                    if glob.is_empty() {
+                        return Err("empty app glob");
+                    }
+                    shortcut.app_scope = Some(unescape(glob)?);
+                } else if let Some(name) = flag.strip_prefix("purpose=") {
+                    let purpose = InputPurpose::from_name(name).ok_or("unknown purpose")?;
+                    shortcut.purpose_scope = Some(purpose);
+                } else {
+                    return Err("unknown flag");
+                }
+            }
+        }
+    }
+    if fields.next().is_some() {
+        return Err("too many fields");
+    }
+    Ok(shortcut)
+}
+
+/// Split flags on commas, except escaped ones (`\,`)
+fn split_flags(s: &str) -> Vec<&str> {
+    let mut flags = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                flags.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    flags.push(s[start..].trim());
+    flags
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> Result<String, &'static str> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('#') => out.push('#'),
+            Some(',') => out.push(','),
+            _ => return Err("invalid escape"),
+        }
+    }
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::engine::buffer::MAX;
+
+    #[test]
+    fn test_template_expansion() {
+        let mut table = ShortcutTable::new();
+        table.add(Shortcut::new("tt", "Phiếu {counter}: {clipboard}"));
+        table.add(Shortcut::immediate("<<", "«{cursor}»"));
+        table.set_clipboard("gõ tắt");
+
+        let m = table.try_match("tt", Some(' '), true).unwrap();
+        assert_eq!(m.output, "Phiếu 1: gõ tắt ");
+        assert_eq!(m.cursor_back, 0);
+        let m = table.try_match("tt", Some(' '), true).unwrap();
+        assert_eq!(m.output, "Phiếu 2: gõ tắt ");
+
+        let m = table.try_match("<<", None, false).unwrap();
+        assert_eq!(m.output, "«»");
+        assert_eq!(m.cursor_back, 1);
+
+        // Cursor counts the appended trigger key
+        table.add(Shortcut::new("ht", "<p>{cursor}</p>"));
+        let m = table.try_match("ht", Some(' '), true).unwrap();
+        assert_eq!((m.output.as_str(), m.cursor_back), ("<p></p> ", 5));
+    }
+
+    #[test]
+    fn test_context_scope() {
+        let mut table = ShortcutTable::new();
+        table.add(Shortcut::new("sig", "Trân trọng").in_app("com.apple.mail"));
+        table.add(Shortcut::new("ls", "danh sách").for_purpose(InputPurpose::Text));
+        table.add(Shortcut::new("vn", "Việt Nam"));
+
+        // Unknown app: only unscoped shortcuts
+        assert!(table.try_match("sig", Some(' '), true).is_none());
+        assert!(table.try_match("vn", Some(' '), true).is_some());
+
+        table.set_context("com.apple.Mail", InputPurpose::Text);
+        assert!(table.try_match("sig", Some(' '), true).is_some());
+        assert!(table.try_match("ls", Some(' '), true).is_some());
+
+        table.set_context("com.apple.Terminal", InputPurpose::Terminal);
+        assert!(table.try_match("sig", Some(' '), true).is_none());
+        assert!(table.try_match("ls", Some(' '), true).is_none());
+        assert!(table.try_match("vn", Some(' '), true).is_some());
+    }
+
+    #[test]
+    fn test_scoped_variants() {
+        let mut table = ShortcutTable::new();
+        table.add(Shortcut::new("sig", "Thân mến"));
+        table.add(Shortcut::new("sig", "Trân trọng").in_app("com.apple.mail"));
+        table.add(Shortcut::new("sig", "Best").in_app("com.tinyspeck.*"));
+        table.add(Shortcut::new("sig", "Thân ái"));
+        assert_eq!(table.len(), 3);
+
+        let sig = |table: &mut ShortcutTable| table.try_match("sig", None, true).unwrap().output;
+        assert_eq!(sig(&mut table), "Thân ái");
+        table.set_context("com.apple.mail", InputPurpose::Text);
+        assert_eq!(sig(&mut table), "Trân trọng");
+        table.set_context("com.tinyspeck.slackmacgap", InputPurpose::Text);
+        assert_eq!(sig(&mut table), "Best");
+
+        assert_eq!(table.remove("sig").len(), 3);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("com.apple.mail", "com.apple.mail"));
+        assert!(glob_match("*.mail*", "com.apple.mail"));
+        assert!(glob_match("org.mozilla.*", "org.mozilla.thunderbird"));
+        assert!(glob_match("com.?oogle.*", "com.google.Chrome"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("com.apple.mail", "com.apple.mailx"));
+        assert!(!glob_match("*.mail", "com.apple.mail.compose"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let mut table = ShortcutTable::new();
+        table.add(Shortcut::new("vn", "Việt Nam"));
+        table.add(Shortcut::immediate("->", "→"));
+        table.add(Shortcut::telex("#sig", "Thân mến,\n\tAn \\o/"));
+        let mut vn = Shortcut::new("VN", "Việt Nam").for_method(InputMethod::Vni);
+        vn.case_mode = CaseMode::MatchCase;
+        vn.enabled = false;
+        table.add(vn);
+        table.add(
+            Shortcut::new("sig", "Trân trọng")
+                .in_app("*.mail")
+                .for_purpose(InputPurpose::Text),
+        );
+        table.add(Shortcut::new("sig", "Best").in_app("com.a,com.b"));
+
+        let text = table.export();
+        assert_eq!(
+            text,
+            "#gonhanh-shortcuts version=1\n\
+             \\#sig\tThân mến,\\n\\tAn \\\\o/\timmediate,telex\n\
+             ->\t→\timmediate\n\
+             VN\tViệt Nam\tmatch-case,vni,disabled\n\
+             sig\tTrân trọng\tapp=*.mail,purpose=text\n\
+             sig\tBest\tapp=com.a\\,com.b\n\
+             vn\tViệt Nam\n"
+        );
+
+        let mut imported = ShortcutTable::new();
+        assert_eq!(imported.import(&text), Ok(6));
+        assert_eq!(imported.export(), text);
+        let sig = imported.iter().find(|s| s.trigger == "#sig").unwrap();
+        assert_eq!(sig.replacement, "Thân mến,\n\tAn \\o/");
+    }
+
+    #[test]
+    fn test_import_unikey() {
+        let text = ";DO NOT DELETE THIS LINE*** version=1 ***\r\n\
+                    vn:Việt Nam\r\n\
+                    \r\n\
+                    => : ⇒\r\n\
+                    url:http://gonhanh.org\r\n";
+        let mut table = ShortcutTable::new();
+        assert_eq!(table.import(text), Ok(3));
+        let arrow = table.lookup("=>").unwrap().1;
+        assert_eq!(arrow.condition, TriggerCondition::Immediate);
+        assert_eq!(arrow.replacement, "⇒");
+        assert_eq!(
+            table.lookup("url").unwrap().1.replacement,
+            "http://gonhanh.org"
+        );
+        assert_eq!(
+            table.lookup("vn").unwrap().1.condition,
+            TriggerCondition::OnWordBoundary
+        );
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let err = |text: &str| {
+            let mut table = ShortcutTable::new();
+            let e = table.import(text).unwrap_err();
+            assert!(table.is_empty(), "nothing applied on error");
+            (e.line, e.reason)
+        };
+        assert_eq!(
+            err("vn:Việt Nam\nno colon here"),
+            (2, "expected trigger:replacement")
+        );
+        assert_eq!(err(":x"), (1, "empty trigger"));
+        assert_eq!(
+            err("#gonhanh-shortcuts version=1\nvn\tViệt\tbold"),
+            (2, "unknown flag")
+        );
+        assert_eq!(
+            err("#gonhanh-shortcuts version=1\nvn\tViệt\tpurpose=chat"),
+            (2, "unknown purpose")
+        );
+        assert_eq!(
+            err("#gonhanh-shortcuts version=1\nvn"),
+            (2, "expected trigger<TAB>replacement")
+        );
+        assert_eq!(
+            err("#gonhanh-shortcuts version=1\nvn\ta\\q"),
+            (2, "invalid escape")
+        );
+        assert_eq!(
+            err("#gonhanh-shortcuts version=9"),
+            (1, "unsupported version")
+        );
+    }
 
     // Helper: Create table with one word-boundary shortcut
     fn table_with_shortcut(trigger: &str, replacement: &str) -> ShortcutTable {
@@ -422,7 +960,7 @@ mod tests {
 
     // Helper: Assert shortcut matches and check output/backspace
     fn assert_shortcut_match(
-        table: &ShortcutTable,
+        table: &mut ShortcutTable,
         buffer: &str,
         key_char: Option<char>,
         is_boundary: bool,
@@ -443,7 +981,7 @@ mod tests {
 
     // Helper: Assert no shortcut match
     fn assert_no_match(
-        table: &ShortcutTable,
+        table: &mut ShortcutTable,
         buffer: &str,
         key_char: Option<char>,
         is_boundary: bool,
@@ -459,9 +997,9 @@ mod tests {
 
     #[test]
     fn test_basic_shortcut() {
-        let table = table_with_shortcut("vn", "Việt Nam");
+        let mut table = table_with_shortcut("vn", "Việt Nam");
         assert_shortcut_match(
-            &table,
+            &mut table,
             "vn",
             Some(' '),
             true,
@@ -473,11 +1011,11 @@ mod tests {
 
     #[test]
     fn test_case_matching() {
-        let table = table_with_shortcut("vn", "Việt Nam");
+        let mut table = table_with_shortcut("vn", "Việt Nam");
 
         // Exact match (lowercase "vn" matches "vn")
         assert_shortcut_match(
-            &table,
+            &mut table,
             "vn",
             Some(' '),
             true,
@@ -487,15 +1025,15 @@ mod tests {
         );
 
         // Uppercase "VN" does NOT match lowercase "vn" (case-sensitive)
-        assert_no_match(&table, "VN", Some(' '), true, InputMethod::All);
+        assert_no_match(&mut table, "VN", Some(' '), true, InputMethod::All);
 
         // Title case "Vn" does NOT match lowercase "vn" (case-sensitive)
-        assert_no_match(&table, "Vn", Some(' '), true, InputMethod::All);
+        assert_no_match(&mut table, "Vn", Some(' '), true, InputMethod::All);
     }
 
     #[test]
     fn test_immediate_shortcut() {
-        let table = table_with_immediate("w", "ư");
+        let mut table = table_with_immediate("w", "ư");
 
         // Immediate triggers without word boundary
         let result = table.try_match("w", None, false);
@@ -507,14 +1045,14 @@ mod tests {
 
     #[test]
     fn test_word_boundary_required() {
-        let table = table_with_shortcut("vn", "Việt Nam");
+        let mut table = table_with_shortcut("vn", "Việt Nam");
 
         // Without word boundary - should not match
-        assert_no_match(&table, "vn", Some('a'), false, InputMethod::All);
+        assert_no_match(&mut table, "vn", Some('a'), false, InputMethod::All);
 
         // With word boundary - should match
         assert_shortcut_match(
-            &table,
+            &mut table,
             "vn",
             Some(' '),
             true,
@@ -548,36 +1086,36 @@ mod tests {
 
     #[test]
     fn test_telex_specific_shortcut() {
-        let table = table_with_telex_shortcut("w", "ư");
+        let mut table = table_with_telex_shortcut("w", "ư");
 
         // Should match for Telex
-        assert_shortcut_match(&table, "w", None, false, "ư", 1, InputMethod::Telex);
+        assert_shortcut_match(&mut table, "w", None, false, "ư", 1, InputMethod::Telex);
 
         // Should NOT match for VNI
-        assert_no_match(&table, "w", None, false, InputMethod::Vni);
+        assert_no_match(&mut table, "w", None, false, InputMethod::Vni);
 
         // Should match for All (fallback)
-        assert_shortcut_match(&table, "w", None, false, "ư", 1, InputMethod::All);
+        assert_shortcut_match(&mut table, "w", None, false, "ư", 1, InputMethod::All);
     }
 
     #[test]
     fn test_vni_specific_shortcut() {
-        let table = table_with_vni_shortcut("7", "ơ");
+        let mut table = table_with_vni_shortcut("7", "ơ");
 
         // Should match for VNI
-        assert_shortcut_match(&table, "7", None, false, "ơ", 1, InputMethod::Vni);
+        assert_shortcut_match(&mut table, "7", None, false, "ơ", 1, InputMethod::Vni);
 
         // Should NOT match for Telex
-        assert_no_match(&table, "7", None, false, InputMethod::Telex);
+        assert_no_match(&mut table, "7", None, false, InputMethod::Telex);
     }
 
     #[test]
     fn test_all_input_method_shortcut() {
-        let table = table_with_shortcut("vn", "Việt Nam");
+        let mut table = table_with_shortcut("vn", "Việt Nam");
 
         // Should match for Telex
         assert_shortcut_match(
-            &table,
+            &mut table,
             "vn",
             Some(' '),
             true,
@@ -588,7 +1126,7 @@ mod tests {
 
         // Should match for VNI
         assert_shortcut_match(
-            &table,
+            &mut table,
             "vn",
             Some(' '),
             true,
@@ -599,7 +1137,7 @@ mod tests {
 
         // Should match for All
         assert_shortcut_match(
-            &table,
+            &mut table,
             "vn",
             Some(' '),
             true,
@@ -662,20 +1200,15 @@ mod tests {
 
     #[test]
     fn test_replacement_validation_truncation() {
-        // Create a very long replacement (100 characters with Vietnamese)
+        // Longer than a `Result` can carry: kept whole (hosts use `ime_key_v2`)
         let long_text = "Đây là một đoạn văn bản rất dài để kiểm tra việc cắt ngắn. Nó có nhiều ký tự tiếng Việt có dấu như ồ, ế, ẫ, ơ, ư.";
-        let char_count = long_text.chars().count();
         assert!(
-            char_count > MAX_REPLACEMENT_LEN,
-            "Test text should exceed limit"
+            long_text.chars().count() > MAX,
+            "Test text should exceed a Result"
         );
 
         let shortcut = Shortcut::new("long", long_text);
-        let result_count = shortcut.replacement.chars().count();
-        assert_eq!(
-            result_count, MAX_REPLACEMENT_LEN,
-            "Should truncate to MAX_REPLACEMENT_LEN"
-        );
+        assert_eq!(shortcut.replacement, long_text, "Should not truncate");
     }
 
     #[test]
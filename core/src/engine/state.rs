@@ -0,0 +1,441 @@
+//! Engine snapshot
+//!
+//! Per-context state of an engine (method, options, the word being typed and
+//! its history) so hosts can keep one per application and swap on focus.
+//! Shortcuts and loaded method tables are shared settings and not included.
+//!
+//! Binary layout, little-endian:
+//!
+//! ```text
+//! "GN" version:u8
+//! method:u8 charset:u8 options:u16 word_flags:u16
+//! buffer                       len:u8, then per char key:u16 bits:u8
+//! caret:u8 breve:u8 u_horn:u8  0xFF = none
+//! last_transform               tag:u8 key:u16 value:u8
+//! raw_input                    len:u16, then per key key:u16 bits:u8
+//! history                      count:u8, then buffers oldest first
+//! spaces_after_commit:u8
+//! shortcut_prefix              len:u8, then UTF-8 bytes
+//! ```
+
+use super::buffer::{Buffer, Char, MAX};
+use super::{Engine, Transform, WordHistory, HISTORY_CAPACITY};
+use crate::data::charset::Charset;
+
+const MAGIC: &[u8; 2] = b"GN";
+
+/// Current snapshot format version
+pub const VERSION: u8 = 1;
+
+/// Encoded `None` for positions
+const NO_POS: u8 = 0xFF;
+
+// Option bits
+const OPT_ENABLED: u16 = 1 << 0;
+const OPT_SKIP_W: u16 = 1 << 1;
+const OPT_ESC_RESTORE: u16 = 1 << 2;
+const OPT_FREE_TONE: u16 = 1 << 3;
+const OPT_MODERN_TONE: u16 = 1 << 4;
+const OPT_ENGLISH_RESTORE: u16 = 1 << 5;
+const OPT_AUTO_CAPITALIZE: u16 = 1 << 6;
+const OPT_ENGLISH_DICTIONARY: u16 = 1 << 7;
+const OPT_STRICT_SYLLABLES: u16 = 1 << 8;
+const OPT_AUTO_ACCENT: u16 = 1 << 9;
+const OPT_CARET_TRACKING: u16 = 1 << 10;
+
+/// Per-word flags, in bit order
+const WORD_FLAG_COUNT: usize = 9;
+
+/// The current word's part of the state, for going back within the word
+///
+/// Options, history and the caret are left alone (see `Engine::word_state`).
+pub(super) struct WordState {
+    buf: Buffer,
+    raw_input: Vec<(u16, bool, bool)>,
+    word_flags: [bool; WORD_FLAG_COUNT],
+    pending_breve_pos: Option<usize>,
+    pending_u_horn_pos: Option<usize>,
+    last_transform: Option<Transform>,
+}
+
+/// Snapshot of an engine's per-context state (see `Engine::snapshot`)
+#[derive(Clone)]
+pub struct EngineState {
+    method: u8,
+    output_charset: Charset,
+    options: u16,
+    word_flags: [bool; WORD_FLAG_COUNT],
+    buf: Buffer,
+    caret: Option<usize>,
+    pending_breve_pos: Option<usize>,
+    pending_u_horn_pos: Option<usize>,
+    last_transform: Option<Transform>,
+    raw_input: Vec<(u16, bool, bool)>,
+    history: Vec<Buffer>,
+    spaces_after_commit: u8,
+    shortcut_prefix: String,
+}
+
+impl EngineState {
+    /// Encode as a versioned binary blob
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(16 + self.buf.len() * 3 + self.raw_input.len() * 3);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.push(self.method);
+        out.push(self.output_charset as u8);
+        out.extend_from_slice(&self.options.to_le_bytes());
+        let flags = (0..WORD_FLAG_COUNT)
+            .filter(|&i| self.word_flags[i])
+            .fold(0u16, |bits, i| bits | 1 << i);
+        out.extend_from_slice(&flags.to_le_bytes());
+
+        write_buffer(&mut out, &self.buf);
+        for pos in [self.caret, self.pending_breve_pos, self.pending_u_horn_pos] {
+            out.push(pos.map_or(NO_POS, |p| p as u8));
+        }
+        let (tag, key, value) = encode_transform(self.last_transform);
+        out.push(tag);
+        out.extend_from_slice(&key.to_le_bytes());
+        out.push(value);
+
+        let raw_input = &self.raw_input[..self.raw_input.len().min(u16::MAX as usize)];
+        out.extend_from_slice(&(raw_input.len() as u16).to_le_bytes());
+        for &(key, caps, shift) in raw_input {
+            out.extend_from_slice(&key.to_le_bytes());
+            out.push(caps as u8 | (shift as u8) << 1);
+        }
+
+        out.push(self.history.len() as u8);
+        for buf in &self.history {
+            write_buffer(&mut out, buf);
+        }
+        out.push(self.spaces_after_commit);
+        // Prefixes are a few symbols ("->"); drop one that can't be encoded
+        let prefix = match self.shortcut_prefix.len() {
+            0..=255 => self.shortcut_prefix.as_bytes(),
+            _ => &[],
+        };
+        out.push(prefix.len() as u8);
+        out.extend_from_slice(prefix);
+        out
+    }
+
+    /// Decode a blob from `to_bytes`
+    ///
+    /// Returns None for other versions and truncated or corrupted data.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let mut r = Reader(data);
+        if r.take(2)? != MAGIC || r.u8()? != VERSION {
+            return None;
+        }
+        let method = r.u8()?;
+        let output_charset = Charset::from_u8(r.u8()?)?;
+        let options = r.u16()?;
+        let flags = r.u16()?;
+        if flags >> WORD_FLAG_COUNT != 0 {
+            return None;
+        }
+        let word_flags = std::array::from_fn(|i| flags & (1 << i) != 0);
+
+        let buf = read_buffer(&mut r)?;
+        let mut pos = || -> Option<Option<usize>> {
+            match r.u8()? {
+                NO_POS => Some(None),
+                p if (p as usize) < buf.len() => Some(Some(p as usize)),
+                _ => None,
+            }
+        };
+        let (caret, pending_breve_pos, pending_u_horn_pos) = (pos()?, pos()?, pos()?);
+        let last_transform = decode_transform(r.u8()?, r.u16()?, r.u8()?)?;
+
+        let raw_len = r.u16()? as usize;
+        let mut raw_input = Vec::with_capacity(raw_len.min(MAX));
+        for _ in 0..raw_len {
+            let key = r.u16()?;
+            let bits = r.u8()?;
+            raw_input.push((key, bits & 1 != 0, bits & 2 != 0));
+        }
+
+        let count = r.u8()? as usize;
+        if count > HISTORY_CAPACITY {
+            return None;
+        }
+        let history = (0..count)
+            .map(|_| read_buffer(&mut r))
+            .collect::<Option<Vec<_>>>()?;
+        let spaces_after_commit = r.u8()?;
+        let prefix_len = r.u8()? as usize;
+        let shortcut_prefix = String::from_utf8(r.take(prefix_len)?.to_vec()).ok()?;
+        if !r.0.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            method,
+            output_charset,
+            options,
+            word_flags,
+            buf,
+            caret,
+            pending_breve_pos,
+            pending_u_horn_pos,
+            last_transform,
+            raw_input,
+            history,
+            spaces_after_commit,
+            shortcut_prefix,
+        })
+    }
+}
+
+impl Engine {
+    /// Capture method, options and the word being typed
+    pub fn snapshot(&self) -> EngineState {
+        let options = [
+            (self.enabled, OPT_ENABLED),
+            (self.skip_w_shortcut, OPT_SKIP_W),
+            (self.esc_restore_enabled, OPT_ESC_RESTORE),
+            (self.free_tone_enabled, OPT_FREE_TONE),
+            (self.modern_tone, OPT_MODERN_TONE),
+            (self.english_auto_restore, OPT_ENGLISH_RESTORE),
+            (self.auto_capitalize, OPT_AUTO_CAPITALIZE),
+            (self.english_dictionary, OPT_ENGLISH_DICTIONARY),
+            (self.strict_syllables, OPT_STRICT_SYLLABLES),
+            (self.auto_accent, OPT_AUTO_ACCENT),
+            (self.caret_tracking, OPT_CARET_TRACKING),
+        ]
+        .iter()
+        .filter(|(on, _)| *on)
+        .fold(0, |bits, (_, bit)| bits | bit);
+
+        EngineState {
+            method: self.method,
+            output_charset: self.output_charset,
+            options,
+            word_flags: self.word_flags(),
+            buf: self.buf.clone(),
+            caret: self.caret,
+            pending_breve_pos: self.pending_breve_pos,
+            pending_u_horn_pos: self.pending_u_horn_pos,
+            last_transform: self.last_transform,
+            raw_input: self.raw_input.clone(),
+            history: self.word_history.iter().cloned().collect(),
+            spaces_after_commit: self.spaces_after_commit,
+            shortcut_prefix: self.shortcut_prefix.clone(),
+        }
+    }
+
+    /// Switch to a previously captured state (shortcuts and tables are kept)
+    pub fn restore(&mut self, state: EngineState) {
+        self.method = state.method;
+        self.output_charset = state.output_charset;
+        self.enabled = state.options & OPT_ENABLED != 0;
+        self.skip_w_shortcut = state.options & OPT_SKIP_W != 0;
+        self.esc_restore_enabled = state.options & OPT_ESC_RESTORE != 0;
+        self.free_tone_enabled = state.options & OPT_FREE_TONE != 0;
+        self.modern_tone = state.options & OPT_MODERN_TONE != 0;
+        self.english_auto_restore = state.options & OPT_ENGLISH_RESTORE != 0;
+        self.auto_capitalize = state.options & OPT_AUTO_CAPITALIZE != 0;
+        self.english_dictionary = state.options & OPT_ENGLISH_DICTIONARY != 0;
+        self.strict_syllables = state.options & OPT_STRICT_SYLLABLES != 0;
+        self.auto_accent = state.options & OPT_AUTO_ACCENT != 0;
+        self.caret_tracking = state.options & OPT_CARET_TRACKING != 0;
+
+        self.set_word_flags(state.word_flags);
+
+        self.buf = state.buf;
+        self.caret = state.caret;
+        self.pending_breve_pos = state.pending_breve_pos;
+        self.pending_u_horn_pos = state.pending_u_horn_pos;
+        self.last_transform = state.last_transform;
+        self.raw_input = state.raw_input;
+        self.word_history = WordHistory::new();
+        for buf in state.history {
+            self.word_history.push(buf);
+        }
+        self.spaces_after_commit = state.spaces_after_commit;
+        self.shortcut_prefix = state.shortcut_prefix;
+        self.long_output = None;
+        self.caret_edit = None;
+        self.undo.clear();
+        self.undo_keys.clear();
+    }
+
+    /// Capture the current word (undo steps, strict syllables)
+    pub(super) fn word_state(&self) -> WordState {
+        WordState {
+            buf: self.buf.clone(),
+            raw_input: self.raw_input.clone(),
+            word_flags: self.word_flags(),
+            pending_breve_pos: self.pending_breve_pos,
+            pending_u_horn_pos: self.pending_u_horn_pos,
+            last_transform: self.last_transform,
+        }
+    }
+
+    /// Go back to a word captured by `word_state`
+    pub(super) fn restore_word_state(&mut self, word: WordState) {
+        self.buf = word.buf;
+        self.raw_input = word.raw_input;
+        self.set_word_flags(word.word_flags);
+        self.pending_breve_pos = word.pending_breve_pos;
+        self.pending_u_horn_pos = word.pending_u_horn_pos;
+        self.last_transform = word.last_transform;
+    }
+
+    /// Per-word flags, in bit order
+    fn word_flags(&self) -> [bool; WORD_FLAG_COUNT] {
+        [
+            self.has_non_letter_prefix,
+            self.stroke_reverted,
+            self.had_mark_revert,
+            self.pending_mark_revert_pop,
+            self.had_any_transform,
+            self.had_vowel_triggered_circumflex,
+            self.restored_pending_clear,
+            self.pending_capitalize,
+            self.auto_capitalize_used,
+        ]
+    }
+
+    fn set_word_flags(&mut self, flags: [bool; WORD_FLAG_COUNT]) {
+        [
+            self.has_non_letter_prefix,
+            self.stroke_reverted,
+            self.had_mark_revert,
+            self.pending_mark_revert_pop,
+            self.had_any_transform,
+            self.had_vowel_triggered_circumflex,
+            self.restored_pending_clear,
+            self.pending_capitalize,
+            self.auto_capitalize_used,
+        ] = flags;
+    }
+}
+
+fn write_buffer(out: &mut Vec<u8>, buf: &Buffer) {
+    out.push(buf.len() as u8);
+    for c in buf.iter() {
+        out.extend_from_slice(&c.key.to_le_bytes());
+        out.push(c.caps as u8 | (c.stroke as u8) << 1 | c.tone << 2 | c.mark << 4);
+    }
+}
+
+fn read_buffer(r: &mut Reader) -> Option<Buffer> {
+    let len = r.u8()? as usize;
+    if len > MAX {
+        return None;
+    }
+    let mut buf = Buffer::new();
+    for _ in 0..len {
+        let mut c = Char::new(r.u16()?, false);
+        let bits = r.u8()?;
+        c.caps = bits & 1 != 0;
+        c.stroke = bits & 2 != 0;
+        c.tone = (bits >> 2) & 0b11;
+        c.mark = bits >> 4;
+        if c.tone > 2 || c.mark > 5 {
+            return None;
+        }
+        buf.push(c);
+    }
+    Some(buf)
+}
+
+fn encode_transform(t: Option<Transform>) -> (u8, u16, u8) {
+    match t {
+        None => (0, 0, 0),
+        Some(Transform::Mark(key, value)) => (1, key, value),
+        Some(Transform::Tone(key, value)) => (2, key, value),
+        Some(Transform::Stroke(key)) => (3, key, 0),
+        Some(Transform::ShortPatternStroke) => (4, 0, 0),
+        Some(Transform::WAsVowel) => (5, 0, 0),
+        Some(Transform::WShortcutSkipped) => (6, 0, 0),
+    }
+}
+
+fn decode_transform(tag: u8, key: u16, value: u8) -> Option<Option<Transform>> {
+    Some(match tag {
+        0 => None,
+        1 => Some(Transform::Mark(key, value)),
+        2 => Some(Transform::Tone(key, value)),
+        3 => Some(Transform::Stroke(key)),
+        4 => Some(Transform::ShortPatternStroke),
+        5 => Some(Transform::WAsVowel),
+        6 => Some(Transform::WShortcutSkipped),
+        _ => return None,
+    })
+}
+
+/// Byte cursor; every read fails on truncated input
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+    use crate::utils::type_word;
+
+    #[test]
+    fn test_roundtrip_continues_word() {
+        let mut e = Engine::new();
+        e.set_method(1); // VNI
+        e.set_modern_tone(false);
+        type_word(&mut e, "xin chao tie6");
+        let blob = e.snapshot().to_bytes();
+
+        // Other app uses Telex with a different word
+        let mut other = Engine::new();
+        type_word(&mut other, "vieet");
+        other.restore(EngineState::from_bytes(&blob).unwrap());
+        assert_eq!(other.get_buffer_string(), "tiê");
+
+        type_word(&mut other, "ng1");
+        assert_eq!(other.get_buffer_string(), "tiếng");
+        assert_eq!(other.snapshot().method, 1);
+        assert!(!other.modern_tone);
+
+        // History came along: backspace over the space re-enters "chao"
+        let mut e = Engine::new();
+        type_word(&mut e, "chao ");
+        let state = EngineState::from_bytes(&e.snapshot().to_bytes()).unwrap();
+        let mut other = Engine::new();
+        other.restore(state);
+        other.on_key(keys::DELETE, false, false);
+        assert_eq!(other.get_buffer_string(), "chao");
+    }
+
+    #[test]
+    fn test_rejects_bad_blobs() {
+        let mut e = Engine::new();
+        type_word(&mut e, "vieet");
+        let blob = e.snapshot().to_bytes();
+
+        assert!(EngineState::from_bytes(&blob[..blob.len() - 1]).is_none());
+        assert!(EngineState::from_bytes(&[blob.as_slice(), &[0]].concat()).is_none());
+        let mut other_version = blob.clone();
+        other_version[2] = VERSION + 1;
+        assert!(EngineState::from_bytes(&other_version).is_none());
+        assert!(EngineState::from_bytes(b"").is_none());
+    }
+}
@@ -0,0 +1,173 @@
+//! Shortcut templates
+//!
+//! Placeholders in a replacement are evaluated when the shortcut expands:
+//!
+//! | Placeholder           | Output                                    |
+//! |-----------------------|-------------------------------------------|
+//! | `{date}`              | 17/10/2026 (`{date:%Y-%m-%d}` → 2026-10-17) |
+//! | `{time}`              | 14:05 (`{time:%H:%M:%S}` → 14:05:09)      |
+//! | `{counter}`           | how many times this shortcut expanded     |
+//! | `{clipboard}`         | text the host put in the clipboard slot   |
+//! | `{cursor}`            | nothing; the caret ends up here           |
+//!
+//! Format codes: `%d %m %Y %y %H %M %S %%`. `{{` and `}}` are literal braces.
+//! Unknown placeholders are kept as typed, so plain replacements with
+//! braces ("{x}") expand unchanged.
+
+/// Values available to placeholders
+pub struct Context<'a> {
+    /// Local time as seconds since 1970-01-01 00:00 (UTC + host offset)
+    pub local_time: i64,
+    /// Expansion count of the shortcut, including this one
+    pub counter: u32,
+    /// Host-provided clipboard slot
+    pub clipboard: &'a str,
+}
+
+/// Evaluated template
+#[derive(Debug, PartialEq)]
+pub struct Expansion {
+    pub text: String,
+    /// Caret position in `text` in chars, from `{cursor}` (first marker wins)
+    pub cursor: Option<usize>,
+}
+
+/// Check whether a replacement needs evaluation
+pub fn is_template(s: &str) -> bool {
+    s.contains('{') || s.contains('}')
+}
+
+/// Evaluate placeholders in `template`
+pub fn expand(template: &str, ctx: &Context) -> Expansion {
+    let mut text = String::with_capacity(template.len());
+    let mut cursor = None;
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        text.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            text.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) else {
+            text.push_str(&tail[..1]);
+            rest = &tail[1..];
+            continue;
+        };
+
+        let body = &tail[1..end];
+        let (name, arg) = match body.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (body, None),
+        };
+        match name {
+            "date" => format_time(ctx.local_time, arg.unwrap_or("%d/%m/%Y"), &mut text),
+            "time" => format_time(ctx.local_time, arg.unwrap_or("%H:%M"), &mut text),
+            "counter" if arg.is_none() => text.push_str(&ctx.counter.to_string()),
+            "clipboard" if arg.is_none() => text.push_str(ctx.clipboard),
+            "cursor" if arg.is_none() => {
+                cursor.get_or_insert(text.chars().count());
+            }
+            _ => text.push_str(&tail[..=end]),
+        }
+        rest = &tail[end + 1..];
+    }
+    text.push_str(rest);
+
+    Expansion { text, cursor }
+}
+
+/// strftime subset over a local timestamp
+fn format_time(local_time: i64, format: &str, out: &mut String) {
+    let days = local_time.div_euclid(86_400);
+    let secs = local_time.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('d') => out.push_str(&format!("{:02}", day)),
+            Some('m') => out.push_str(&format!("{:02}", month)),
+            Some('Y') => out.push_str(&year.to_string()),
+            Some('y') => out.push_str(&format!("{:02}", year.rem_euclid(100))),
+            Some('H') => out.push_str(&format!("{:02}", secs / 3600)),
+            Some('M') => out.push_str(&format!("{:02}", secs / 60 % 60)),
+            Some('S') => out.push_str(&format!("{:02}", secs % 60)),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+}
+
+/// Days since 1970-01-01 → (year, month, day), proleptic Gregorian
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-10-17 14:05:09
+    const NOW: i64 = 1_792_245_909;
+
+    fn ctx() -> Context<'static> {
+        Context {
+            local_time: NOW,
+            counter: 3,
+            clipboard: "gonhanh.org",
+        }
+    }
+
+    fn text(template: &str) -> String {
+        expand(template, &ctx()).text
+    }
+
+    #[test]
+    fn test_date_time() {
+        assert_eq!(text("Ngày {date}"), "Ngày 17/10/2026");
+        assert_eq!(text("{date:%Y-%m-%d} {time}"), "2026-10-17 14:05");
+        assert_eq!(text("{time:%H:%M:%S} {date:%y}"), "14:05:09 26");
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn test_counter_clipboard_cursor() {
+        assert_eq!(text("Phiếu #{counter}"), "Phiếu #3");
+        assert_eq!(text("Xem {clipboard}"), "Xem gonhanh.org");
+
+        let e = expand("<b>{cursor}</b>{cursor}", &ctx());
+        assert_eq!(e.text, "<b></b>");
+        assert_eq!(e.cursor, Some(3));
+        assert_eq!(expand("không", &ctx()).cursor, None);
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        assert_eq!(text("{{date}}"), "{date}");
+        assert_eq!(text("{x} {counter:5} }{"), "{x} {counter:5} }{");
+        assert_eq!(text("a { b"), "a { b");
+        assert!(!is_template("Việt Nam"));
+    }
+}
@@ -0,0 +1,309 @@
+//! Undo of IME actions
+//!
+//! Every key the engine rewrites (mark, tone, stroke, W as a vowel) pushes a
+//! step for the current word; `Engine::undo` reverts the latest one, leaving
+//! the keys typed since then as plain letters: "vieetj" → "việt" → undo →
+//! "viêtj" → undo → "vieetj".
+//!
+//! Rewrites at a word boundary (shortcut expansion, auto-restore) can be
+//! undone only until the next key: "vn " → "Việt Nam " → undo → "vn ".
+
+use super::buffer::Char;
+use super::state::WordState;
+use super::{break_key_to_char, encode_result, Action, Engine, Result};
+use crate::data::keys;
+use crate::utils;
+
+/// One undoable step
+pub(super) enum UndoEntry {
+    /// Rewrite inside the word
+    Word {
+        /// Word state before the key
+        word: Box<WordState>,
+        /// Index of the key in `undo_keys`
+        key_index: usize,
+    },
+    /// Rewrite that ended the word
+    Boundary {
+        /// Text the rewrite left on screen
+        shown: String,
+        /// Text to put back: what it replaced plus the key
+        typed: String,
+    },
+}
+
+/// State captured before a key, for recording its step
+pub(super) struct UndoStart {
+    /// None for word-ending keys (they can only make a boundary step)
+    word: Option<WordState>,
+    /// Screen text of the current word (shortcut prefix included)
+    before: String,
+}
+
+impl Engine {
+    /// Revert the latest IME action
+    ///
+    /// Returns the edit replacing the rewritten text with what was typed,
+    /// or `Action::None` when there is nothing to undo.
+    pub fn undo(&mut self) -> Result {
+        let result = self.undo_step();
+        self.record_undo(&result);
+        result
+    }
+
+    /// `undo` without recording
+    fn undo_step(&mut self) -> Result {
+        self.long_output = None;
+        self.caret_edit = None;
+        let Some(entry) = self.undo.pop() else {
+            return Result::none();
+        };
+
+        let (current, text) = match entry {
+            UndoEntry::Boundary { shown, typed } => {
+                self.reset_all();
+                self.undo_keys.clear();
+                (shown, typed)
+            }
+            UndoEntry::Word { word, key_index } => {
+                let current = self.buf.to_full_string();
+                let keys = self.undo_keys.split_off(key_index);
+                self.restore_word_state(*word);
+                self.last_transform = None;
+
+                // Keys since the step come back as typed
+                let mut literal = String::new();
+                let mut plain = true;
+                for &(key, caps, shift) in &keys {
+                    literal.extend(utils::key_to_char_ext(key, caps, shift));
+                    plain &= utils::key_to_char(key, caps).is_some();
+                }
+                let mut text = self.buf.to_full_string();
+                text.push_str(&literal);
+                if plain {
+                    for &(key, caps, shift) in &keys {
+                        self.buf.push(Char::new(key, caps));
+                        self.raw_input.push((key, caps, shift));
+                    }
+                    self.undo_keys.extend(keys);
+                } else {
+                    // Symbols can't live in the buffer: the word ends here
+                    self.reset();
+                    self.undo.clear();
+                    self.undo_keys.clear();
+                }
+                (current, text)
+            }
+        };
+
+        // Only resend from the first changed char
+        let same = current
+            .chars()
+            .zip(text.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let backspace = current.chars().count() - same;
+        let chars: Vec<char> = text.chars().skip(same).collect();
+        encode_result(
+            Result::send(backspace as u8, &chars),
+            &current,
+            self.output_charset,
+        )
+    }
+
+    /// Number of steps `undo` can revert
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Capture state before a key (None when the key can't start a step)
+    pub(super) fn undo_begin(&mut self, key: u16, ctrl: bool, shift: bool) -> Option<UndoStart> {
+        // A boundary rewrite is only undoable right after it
+        if matches!(self.undo.last(), Some(UndoEntry::Boundary { .. })) {
+            self.undo.clear();
+        }
+        // Deleting or editing at the caret changes text the steps refer to
+        if ctrl || self.caret.is_some() || matches!(key, keys::DELETE | keys::LEFT) {
+            self.undo.clear();
+            self.undo_keys.clear();
+            return None;
+        }
+        let mut before = self.shortcut_prefix.clone();
+        before.push_str(&self.buf.to_full_string());
+        let word = (!keys::is_break_ext(key, shift)).then(|| self.word_state());
+        Some(UndoStart { word, before })
+    }
+
+    /// Record the step made by a key, given its (Unicode) result
+    pub(super) fn undo_end(
+        &mut self,
+        start: Option<UndoStart>,
+        key: u16,
+        caps: bool,
+        shift: bool,
+        result: &Result,
+    ) {
+        let Some(start) = start else {
+            return;
+        };
+        let key_char = typed_char(key, caps, shift);
+        let rewrote = result.action == Action::Send as u8
+            && !(result.backspace == 0
+                && result.count == 1
+                && key_char.is_some_and(|c| result.chars[0] == c as u32));
+
+        if self.buf.is_empty() || keys::is_break_ext(key, shift) {
+            self.undo.clear();
+            self.undo_keys.clear();
+            if rewrote {
+                let mut shown = match &self.long_output {
+                    Some(text) => text.clone(),
+                    None => result.chars[..result.count as usize]
+                        .iter()
+                        .filter_map(|&c| char::from_u32(c))
+                        .collect(),
+                };
+                let replaced = start.before.chars().count();
+                let skip = replaced.saturating_sub(result.backspace as usize);
+                let mut typed: String = start.before.chars().skip(skip).collect();
+                if let Some(c) = key_char {
+                    typed.push(c);
+                    // Punctuation after a restore is typed by the host (space is resent)
+                    if key != keys::SPACE
+                        && keys::is_break_ext(key, shift)
+                        && !result.key_consumed()
+                    {
+                        shown.push(c);
+                    }
+                }
+                // Resending the same text (e.g., a word restored before the space)
+                if shown != typed {
+                    self.undo.push(UndoEntry::Boundary { shown, typed });
+                }
+            }
+            return;
+        }
+
+        let key_index = self.undo_keys.len();
+        self.undo_keys.push((key, caps, shift));
+        if let (true, Some(word)) = (rewrote, start.word) {
+            self.undo.push(UndoEntry::Word {
+                word: Box::new(word),
+                key_index,
+            });
+        }
+    }
+}
+
+/// Text a key types when the engine leaves it alone
+fn typed_char(key: u16, caps: bool, shift: bool) -> Option<char> {
+    match key {
+        keys::SPACE => Some(' '),
+        keys::TAB => Some('\t'),
+        keys::RETURN | keys::ENTER => Some('\n'),
+        _ => utils::key_to_char_ext(key, caps, shift).or_else(|| break_key_to_char(key, shift)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::shortcut::Shortcut;
+    use crate::utils::type_word;
+
+    /// Apply an undo to `screen`
+    fn undo(e: &mut Engine, screen: &mut String) {
+        let r = e.undo();
+        assert_eq!(
+            r.action,
+            Action::Send as u8,
+            "nothing to undo in {:?}",
+            screen
+        );
+        for _ in 0..r.backspace {
+            screen.pop();
+        }
+        screen.extend(
+            r.chars[..r.count as usize]
+                .iter()
+                .filter_map(|&c| char::from_u32(c)),
+        );
+    }
+
+    #[test]
+    fn test_word_steps() {
+        let mut e = Engine::new();
+        let mut screen = type_word(&mut e, "vieetj");
+        assert_eq!(screen, "việt");
+        assert_eq!(e.undo_len(), 2);
+
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "viêtj");
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "vieetj");
+        assert_eq!(e.undo().action, Action::None as u8);
+
+        // Typing continues from the restored letters
+        screen.push_str(&type_word(&mut e, "s"));
+        assert_eq!(screen, "vieetjs");
+
+        // Options changed mid-word stay as set
+        e.clear_all();
+        let mut screen = type_word(&mut e, "aa");
+        e.set_english_auto_restore(true);
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "aa");
+        assert!(e.config().english_auto_restore);
+    }
+
+    #[test]
+    fn test_stroke_and_w() {
+        let mut e = Engine::new();
+        let mut screen = type_word(&mut e, "dd");
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "dd");
+
+        e.clear_all();
+        let mut screen = type_word(&mut e, "w");
+        assert_eq!(screen, "ư");
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "w");
+    }
+
+    #[test]
+    fn test_boundary_steps() {
+        let mut e = Engine::new();
+        e.shortcuts_mut().add(Shortcut::new("vn", "Việt Nam"));
+        let mut screen = type_word(&mut e, "vn ");
+        assert_eq!(screen, "Việt Nam ");
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "vn ");
+
+        // Only right after the rewrite
+        e.clear_all();
+        type_word(&mut e, "vn a");
+        assert_eq!(e.undo().action, Action::None as u8);
+
+        let mut e = Engine::new();
+        e.set_english_auto_restore(true);
+        let mut screen = type_word(&mut e, "use ");
+        assert_eq!(screen, "use ");
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "ué ");
+
+        // Restored inside the word
+        e.clear_all();
+        let mut screen = type_word(&mut e, "text");
+        assert_eq!(screen, "text");
+        undo(&mut e, &mut screen);
+        assert_eq!(screen, "tẽt");
+    }
+
+    #[test]
+    fn test_delete_clears() {
+        let mut e = Engine::new();
+        type_word(&mut e, "as");
+        e.on_key(keys::DELETE, false, false);
+        assert_eq!(e.undo_len(), 0);
+    }
+}
@@ -4,9 +4,10 @@
 //! Uses valid patterns from docs/vietnamese-language-system.md Section 7.6.1
 
 use super::syllable::{parse, Syllable};
-use crate::data::chars::tone;
+use crate::data::chars::{mark, parse_char, to_char, tone};
 use crate::data::constants;
 use crate::data::keys;
+use crate::data::syllables;
 
 /// Validation result
 #[derive(Debug, Clone, PartialEq)]
@@ -17,6 +18,8 @@ pub enum ValidationResult {
     InvalidSpelling,
     InvalidVowelPattern,
     NoVowel,
+    /// Tone mark on the wrong vowel, or more than one (reported by `spell`)
+    InvalidTonePlacement,
 }
 
 impl ValidationResult {
@@ -318,6 +321,46 @@ pub fn is_valid_for_transform(buffer_keys: &[u16]) -> bool {
     true
 }
 
+/// Check if `word` is a Vietnamese syllable in use (any case, one tone mark)
+///
+/// Stricter than `validate`: "quuyn" is well-formed but no word.
+/// Finals c, ch, p, t only take sắc or nặng ("tét" yes, "tèt" no).
+pub fn is_known_syllable(word: &str) -> bool {
+    split_tone(word)
+        .is_some_and(|(base, mark)| syllables::contains(&base) && fits_final(&base, mark))
+}
+
+/// Check if `word` can still grow into a known syllable ("nghiê" → "nghiêng")
+pub fn is_known_syllable_prefix(word: &str) -> bool {
+    split_tone(word)
+        .is_some_and(|(base, mark)| syllables::has_prefix(&base) && fits_final(&base, mark))
+}
+
+/// Lowercase toneless form and tone mark of a word (None for two marks)
+fn split_tone(word: &str) -> Option<(String, u8)> {
+    let mut base = String::with_capacity(word.len());
+    let mut tone_mark = mark::NONE;
+    for c in word.chars().flat_map(char::to_lowercase) {
+        match parse_char(c) {
+            Some(p) if p.mark != mark::NONE => {
+                if tone_mark != mark::NONE {
+                    return None;
+                }
+                tone_mark = p.mark;
+                base.push(to_char(p.key, false, p.tone, mark::NONE)?);
+            }
+            _ => base.push(c),
+        }
+    }
+    Some((base, tone_mark))
+}
+
+/// Stop finals (c, ch, p, t) only take sắc or nặng
+fn fits_final(base: &str, tone_mark: u8) -> bool {
+    let stop = base.ends_with(['c', 'p', 't']) || base.ends_with("ch");
+    !stop || matches!(tone_mark, mark::NONE | mark::SAC | mark::NANG)
+}
+
 /// Check if the buffer shows patterns that suggest foreign word input.
 ///
 /// This is a heuristic to detect when the user is likely typing a foreign word
@@ -572,4 +615,23 @@ mod tests {
             "'ăi' should be invalid"
         );
     }
+
+    #[test]
+    fn test_known_syllable() {
+        for word in [
+            "việt", "Nguyễn", "NGHIÊNG", "khuyết", "gì", "được", "tét", "hoà", "hòa",
+        ] {
+            assert!(is_known_syllable(word), "'{}' should be known", word);
+        }
+        // Well-formed but not in use, wrong tone for the final, two marks
+        for word in ["quuyn", "tèt", "chỉch", "viếtj", "áà"] {
+            assert!(!is_known_syllable(word), "'{}' should be unknown", word);
+        }
+        assert!(is_valid(&keys_from_str("quuyn")));
+
+        assert!(is_known_syllable_prefix("nghiê"));
+        assert!(is_known_syllable_prefix("ngư"));
+        assert!(!is_known_syllable_prefix("quu"));
+        assert!(!is_known_syllable_prefix("mĩx"));
+    }
 }
@@ -0,0 +1,181 @@
+//! Diacritic stripping and search folding
+//!
+//! Search keys for matching typed queries against Vietnamese text:
+//!
+//! ```
+//! use gonhanh_core::fold;
+//!
+//! assert_eq!(fold::strip_diacritics("Đà Nẵng"), "Da Nang");
+//! assert_eq!(fold::fold("Nguyễn Văn A"), "nguyen van a");
+//!
+//! // Match ranges point into the accented source
+//! let source = "Phở Hà Nội";
+//! let range = fold::find(source, "ha noi").unwrap();
+//! assert_eq!(&source[range], "Hà Nội");
+//! ```
+//!
+//! Letters fold to their base key, so "ư", "ơ" and "ă" match "u", "o" and
+//! "a", and "đ" matches "d". Combining marks (NFD text) are dropped.
+
+use crate::data::chars::parse_char;
+use crate::utils;
+use std::ops::Range;
+
+/// `text` without tone marks, vowel diacritics or stroke, case kept
+pub fn strip_diacritics(text: &str) -> String {
+    text.chars()
+        .filter(|&c| !is_combining(c))
+        .map(|c| base_char(c).unwrap_or(c))
+        .collect()
+}
+
+/// Search key of `text`: diacritics stripped and lowercased
+pub fn fold(text: &str) -> String {
+    let mut folder = Folder::new();
+    folder.push_str(text);
+    folder.text
+}
+
+/// Source byte range of the first match of `query` in `text`, both folded
+///
+/// An empty query matches nothing.
+pub fn find(text: &str, query: &str) -> Option<Range<usize>> {
+    let query = fold(query);
+    if query.is_empty() {
+        return None;
+    }
+    let mut folder = Folder::new();
+    folder.push_str(text);
+    let start = folder.as_str().find(&query)?;
+    Some(folder.source_range(start..start + query.len()))
+}
+
+/// Incremental folder keeping a map back to the source
+///
+/// Feed the source in chunks (e.g., lines as they load); byte offsets
+/// count from the start of the first chunk.
+#[derive(Clone, Debug, Default)]
+pub struct Folder {
+    text: String,
+    /// Per folded char: (folded byte offset, source byte range)
+    map: Vec<(usize, Range<usize>)>,
+    /// Source bytes consumed so far
+    source_len: usize,
+}
+
+impl Folder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold the next chunk of source text
+    pub fn push_str(&mut self, chunk: &str) {
+        for (offset, c) in chunk.char_indices() {
+            let source = self.source_len + offset..self.source_len + offset + c.len_utf8();
+            if is_combining(c) {
+                // Part of the previous letter: highlight them together
+                if let Some((_, range)) = self.map.last_mut() {
+                    range.end = source.end;
+                }
+                continue;
+            }
+            match base_char(c) {
+                Some(base) => self.push_folded(base.to_ascii_lowercase(), &source),
+                None => {
+                    for lower in c.to_lowercase() {
+                        self.push_folded(lower, &source);
+                    }
+                }
+            }
+        }
+        self.source_len += chunk.len();
+    }
+
+    fn push_folded(&mut self, c: char, source: &Range<usize>) {
+        self.map.push((self.text.len(), source.clone()));
+        self.text.push(c);
+    }
+
+    /// Folded text so far
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Source byte range covering a folded byte range
+    ///
+    /// Ranges cover whole source chars, so a match on "e" in "Việt"
+    /// highlights "ệ".
+    pub fn source_range(&self, folded: Range<usize>) -> Range<usize> {
+        // Index of the folded char containing byte `at`
+        let char_at = |at: usize| self.map.partition_point(|(start, _)| *start <= at) - 1;
+        if folded.start >= self.text.len() {
+            return self.source_len..self.source_len;
+        }
+        let first = &self.map[char_at(folded.start)].1;
+        if folded.end <= folded.start {
+            return first.start..first.start;
+        }
+        let last = &self.map[char_at((folded.end - 1).min(self.text.len() - 1))].1;
+        first.start..last.end
+    }
+
+    /// Start over with an empty source
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Base letter of a Vietnamese letter ("ệ" → 'e', "Đ" → 'D'), None for others
+fn base_char(c: char) -> Option<char> {
+    if !c.is_alphabetic() || c.is_ascii() {
+        return None;
+    }
+    let p = parse_char(c)?;
+    utils::key_to_char(p.key, p.caps)
+}
+
+/// Combining diacritical mark (decomposed text)
+fn is_combining(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_and_fold() {
+        assert_eq!(strip_diacritics("Nguyễn"), "Nguyen");
+        assert_eq!(strip_diacritics("ĐƯỜNG phố, 12°C"), "DUONG pho, 12°C");
+        // Decomposed: "e" + circumflex + dot below
+        assert_eq!(strip_diacritics("Vie\u{302}\u{323}t"), "Viet");
+        assert_eq!(fold("Thành phố Hồ Chí Minh"), "thanh pho ho chi minh");
+        assert_eq!(fold("ÄÖ"), "äö");
+    }
+
+    #[test]
+    fn test_source_range() {
+        let mut folder = Folder::new();
+        folder.push_str("Đà ");
+        folder.push_str("Nẵng");
+        assert_eq!(folder.as_str(), "da nang");
+        let source = "Đà Nẵng";
+        assert_eq!(&source[folder.source_range(3..7)], "Nẵng");
+        assert_eq!(&source[folder.source_range(1..2)], "à");
+        assert_eq!(folder.source_range(2..2), 4..4);
+        assert_eq!(folder.source_range(7..7), source.len()..source.len());
+
+        // Combining marks go with their letter
+        let nfd = "Vie\u{302}\u{323}t";
+        assert_eq!(find(nfd, "e"), Some(2..7));
+    }
+
+    #[test]
+    fn test_find() {
+        let text = "Bún bò Huế, Phở Hà Nội";
+        assert_eq!(find(text, "HUE").map(|r| &text[r]), Some("Huế"));
+        assert_eq!(find(text, "pho ha").map(|r| &text[r]), Some("Phở Hà"));
+        assert_eq!(find(text, "Sài Gòn"), None);
+        assert_eq!(find(text, ""), None);
+    }
+}
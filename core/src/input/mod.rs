@@ -3,14 +3,20 @@
 //! Defines key mappings for Vietnamese input methods.
 //! Engine handles all pattern matching based on buffer scan.
 
+pub mod table;
 pub mod telex;
+pub mod viqr;
 pub mod vni;
 
+pub use table::TableMethod;
 pub use telex::Telex;
+pub use viqr::Viqr;
 pub use vni::Vni;
 
 use crate::data::chars::tone;
 use crate::data::keys;
+use std::ops::Deref;
+use std::sync::Arc;
 
 /// Shared tone target constants
 pub const CIRCUMFLEX_TARGETS: &[u16] = &[keys::A, keys::E, keys::O];
@@ -25,7 +31,7 @@ pub enum ToneType {
     Circumflex,
     /// Horn: ơ, ư (and ă for Telex)
     Horn,
-    /// Breve: ă (VNI only)
+    /// Breve: ă (VNI, VIQR)
     Breve,
 }
 
@@ -51,7 +57,7 @@ pub trait Method {
 
     /// Get valid targets for tone key
     /// Returns list of vowel keys this tone can apply to
-    fn tone_targets(&self, key: u16) -> &'static [u16];
+    fn tone_targets(&self, key: u16) -> &[u16];
 
     /// Check if key is stroke modifier (d → đ)
     fn stroke(&self, key: u16) -> bool;
@@ -63,11 +69,36 @@ pub trait Method {
 /// Static method instances (zero-sized types, no heap allocation)
 static TELEX: Telex = Telex;
 static VNI: Vni = Vni;
+static VIQR: Viqr = Viqr;
+
+/// Method id for the user-defined table loaded into the engine
+pub const TABLE: u8 = 0xFF;
 
 /// Get method by id (returns static reference, no allocation)
 pub fn get(id: u8) -> &'static dyn Method {
     match id {
         1 => &VNI,
+        2 => &VIQR,
         _ => &TELEX,
     }
 }
+
+/// Active method: a built-in static or a shared loaded table
+///
+/// Cheap to clone, so callers can hold it while mutating engine state.
+#[derive(Clone)]
+pub enum MethodRef {
+    Builtin(&'static dyn Method),
+    Table(Arc<TableMethod>),
+}
+
+impl Deref for MethodRef {
+    type Target = dyn Method;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            MethodRef::Builtin(m) => *m,
+            MethodRef::Table(t) => t.as_ref(),
+        }
+    }
+}
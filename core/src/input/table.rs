@@ -0,0 +1,278 @@
+//! Table Input Method
+//!
+//! User-defined key mappings loaded from a small TOML-like definition,
+//! so layouts like "Simple Telex" or team-specific schemes need no core release.
+//!
+//! ```text
+//! # Simple Telex
+//! name = "Simple Telex"
+//!
+//! [marks]
+//! s = "sac"
+//! f = "huyen"
+//! r = "hoi"
+//! x = "nga"
+//! j = "nang"
+//!
+//! [tones]
+//! a = "circumflex a"
+//! e = "circumflex e"
+//! o = "circumflex o"
+//! w = "horn a o u"
+//!
+//! [keys]
+//! stroke = "d"
+//! remove = "z"
+//! ```
+//!
+//! - Keys are single characters on a US layout; shifted symbols ('^', '?')
+//!   bind the shifted key, so '6' and '^' can mean different things
+//! - Tone values: `circumflex`, `horn` or `breve`, followed by target vowels
+//! - Tables use the generic modifier rules (like VNI): Telex-only heuristics
+//!   such as standalone w → ư stay with the built-in Telex method
+
+use super::{Method, ToneType};
+use crate::data::keys;
+use crate::lines::{self, LineError};
+use crate::utils::char_to_key_ext;
+
+/// Tone key entry: key, tone type, target vowels
+#[derive(Debug, Clone, PartialEq)]
+struct ToneEntry {
+    key: u16,
+    tone: ToneType,
+    targets: Vec<u16>,
+}
+
+/// Data-driven input method built from a definition table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableMethod {
+    name: String,
+    marks: Vec<(u16, u8)>,
+    tones: Vec<ToneEntry>,
+    stroke: Vec<u16>,
+    remove: Vec<u16>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Root,
+    Marks,
+    Tones,
+    Keys,
+}
+
+impl TableMethod {
+    /// Parse a method definition (see module docs for the format)
+    pub fn parse(definition: &str) -> Result<Self, LineError> {
+        let mut table = TableMethod::default();
+        let mut section = Section::Root;
+
+        for (line_no, line) in lines::content_lines(definition) {
+            let err = |reason| LineError {
+                line: line_no,
+                reason,
+            };
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = match name.trim() {
+                    "marks" => Section::Marks,
+                    "tones" => Section::Tones,
+                    "keys" => Section::Keys,
+                    _ => return Err(err("unknown section")),
+                };
+                continue;
+            }
+
+            let (lhs, rhs) = lines::key_value(line).map_err(err)?;
+            let lhs = lines::unquote(lhs);
+
+            match section {
+                Section::Root => match lhs {
+                    "name" => table.name = rhs.to_string(),
+                    _ => return Err(err("unknown setting")),
+                },
+                Section::Marks => {
+                    let key = parse_key(lhs).ok_or(err("invalid key"))?;
+                    let mark = parse_mark(rhs).ok_or(err("unknown mark"))?;
+                    table.marks.push((key, mark));
+                }
+                Section::Tones => {
+                    let key = parse_key(lhs).ok_or(err("invalid key"))?;
+                    let mut parts = rhs.split_whitespace();
+                    let tone = match parts.next() {
+                        Some("circumflex") => ToneType::Circumflex,
+                        Some("horn") => ToneType::Horn,
+                        Some("breve") => ToneType::Breve,
+                        _ => return Err(err("unknown tone")),
+                    };
+                    let targets = parts
+                        .flat_map(str::chars)
+                        .map(|c| parse_vowel(c, tone))
+                        .collect::<Option<Vec<u16>>>()
+                        .ok_or(err("invalid tone target"))?;
+                    if targets.is_empty() {
+                        return Err(err("tone needs target vowels"));
+                    }
+                    table.tones.push(ToneEntry { key, tone, targets });
+                }
+                Section::Keys => {
+                    let keys = rhs
+                        .chars()
+                        .map(|c| parse_key(c.encode_utf8(&mut [0; 4])))
+                        .collect::<Option<Vec<u16>>>()
+                        .ok_or(err("invalid key"))?;
+                    match lhs {
+                        "stroke" => table.stroke = keys,
+                        "remove" => table.remove = keys,
+                        _ => return Err(err("unknown key setting")),
+                    }
+                }
+            }
+        }
+
+        if table.marks.is_empty() && table.tones.is_empty() && table.stroke.is_empty() {
+            return Err(LineError {
+                line: 0,
+                reason: "definition has no modifier keys",
+            });
+        }
+        Ok(table)
+    }
+
+    /// Display name from the definition (may be empty)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Method for TableMethod {
+    fn mark(&self, key: u16) -> Option<u8> {
+        self.marks.iter().find(|(k, _)| *k == key).map(|(_, m)| *m)
+    }
+
+    fn tone(&self, key: u16) -> Option<ToneType> {
+        self.tones.iter().find(|t| t.key == key).map(|t| t.tone)
+    }
+
+    fn tone_targets(&self, key: u16) -> &[u16] {
+        self.tones
+            .iter()
+            .find(|t| t.key == key)
+            .map(|t| t.targets.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn stroke(&self, key: u16) -> bool {
+        self.stroke.contains(&key)
+    }
+
+    fn remove(&self, key: u16) -> bool {
+        self.remove.contains(&key)
+    }
+}
+
+/// Single character → method key (lowercase letters only, shifted symbols flagged)
+fn parse_key(s: &str) -> Option<u16> {
+    let mut chars = s.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() || c.is_ascii_uppercase() {
+        return None;
+    }
+    let (key, _, shift) = char_to_key_ext(c)?;
+    if matches!(
+        key,
+        keys::SPACE | keys::TAB | keys::RETURN | keys::DELETE | keys::ESC
+    ) {
+        return None;
+    }
+    Some(keys::with_shift(key, shift))
+}
+
+fn parse_mark(s: &str) -> Option<u8> {
+    match s {
+        "sac" => Some(1),
+        "huyen" => Some(2),
+        "hoi" => Some(3),
+        "nga" => Some(4),
+        "nang" => Some(5),
+        _ => None,
+    }
+}
+
+/// Target vowel for a tone, limited to vowels that can carry it
+fn parse_vowel(c: char, tone: ToneType) -> Option<u16> {
+    let key = match c {
+        'a' => keys::A,
+        'e' => keys::E,
+        'o' => keys::O,
+        'u' => keys::U,
+        _ => return None,
+    };
+    let valid = match tone {
+        ToneType::Circumflex => super::CIRCUMFLEX_TARGETS.contains(&key),
+        ToneType::Horn => super::HORN_TARGETS_TELEX.contains(&key),
+        ToneType::Breve => super::BREVE_TARGETS.contains(&key),
+    };
+    valid.then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIMPLE_TELEX: &str = r#"
+# Simple Telex
+name = "Simple Telex"
+
+[marks]
+s = "sac"
+f = "huyen"
+r = "hoi"
+x = "nga"
+j = "nang"
+
+[tones]
+a = "circumflex a"
+e = "circumflex e"
+o = "circumflex o"
+w = "horn a o u"
+
+[keys]
+stroke = "d"
+remove = "z"
+"#;
+
+    #[test]
+    fn test_parse() {
+        let t = TableMethod::parse(SIMPLE_TELEX).unwrap();
+        assert_eq!(t.name(), "Simple Telex");
+        assert_eq!(t.mark(keys::S), Some(1));
+        assert_eq!(t.mark(keys::J), Some(5));
+        assert_eq!(t.tone(keys::W), Some(ToneType::Horn));
+        assert_eq!(t.tone_targets(keys::W), &[keys::A, keys::O, keys::U]);
+        assert_eq!(t.tone_targets(keys::A), &[keys::A]);
+        assert!(t.stroke(keys::D));
+        assert!(t.remove(keys::Z));
+        assert_eq!(t.mark(keys::B), None);
+    }
+
+    #[test]
+    fn test_shifted_symbols() {
+        let t = TableMethod::parse("[marks]\n\"'\" = \"sac\"\n\"?\" = \"hoi\"\n").unwrap();
+        assert_eq!(t.mark(keys::QUOTE), Some(1));
+        assert_eq!(t.mark(keys::with_shift(keys::SLASH, true)), Some(3));
+        assert_eq!(t.mark(keys::SLASH), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let line = |def: &str| TableMethod::parse(def).unwrap_err().line;
+        assert_eq!(line("[marks]\ns = sac\n[bogus]"), 3);
+        assert_eq!(line("[marks]\nss = sac"), 2);
+        assert_eq!(line("[marks]\ns = acute"), 2);
+        assert_eq!(line("[tones]\nw = horn e"), 2);
+        assert_eq!(line("[tones]\nw = horn"), 2);
+        assert_eq!(line("[keys]\nstroke"), 2);
+        assert_eq!(line("# empty\n"), 0);
+    }
+}
@@ -0,0 +1,94 @@
+//! VIQR Input Method
+//!
+//! Key mappings:
+//! - Marks: '=sắc, `=huyền, ?=hỏi, ~=ngã, .=nặng
+//! - Tones: ^=circumflex, +=horn, (=breve
+//! - Stroke: dd → đ (applied by the engine: 'd' is a plain letter here)
+//! - Remove: none
+//!
+//! Modifiers are punctuation keys: they only compose while a word is being
+//! typed, and stay punctuation when nothing can take them ("ok." → "ok.").
+//! Typing a modifier twice leaves it as a literal ("a''" → "a'").
+
+use super::{Method, ToneType, BREVE_TARGETS, CIRCUMFLEX_TARGETS, HORN_TARGETS_VNI};
+use crate::data::keys;
+
+pub struct Viqr;
+
+/// Shifted key codes for VIQR symbols
+const QUESTION: u16 = keys::SLASH | keys::SHIFT;
+const TILDE: u16 = keys::BACKQUOTE | keys::SHIFT;
+const CARET: u16 = keys::N6 | keys::SHIFT;
+const PLUS: u16 = keys::EQUAL | keys::SHIFT;
+const LPAREN: u16 = keys::N9 | keys::SHIFT;
+
+impl Method for Viqr {
+    fn mark(&self, key: u16) -> Option<u8> {
+        match key {
+            keys::QUOTE => Some(1),     // sắc
+            keys::BACKQUOTE => Some(2), // huyền
+            QUESTION => Some(3),        // hỏi
+            TILDE => Some(4),           // ngã
+            keys::DOT => Some(5),       // nặng
+            _ => None,
+        }
+    }
+
+    fn tone(&self, key: u16) -> Option<ToneType> {
+        match key {
+            CARET => Some(ToneType::Circumflex),
+            PLUS => Some(ToneType::Horn),
+            LPAREN => Some(ToneType::Breve),
+            _ => None,
+        }
+    }
+
+    fn tone_targets(&self, key: u16) -> &'static [u16] {
+        match key {
+            CARET => CIRCUMFLEX_TARGETS,
+            PLUS => HORN_TARGETS_VNI,
+            LPAREN => BREVE_TARGETS,
+            _ => &[],
+        }
+    }
+
+    fn stroke(&self, _key: u16) -> bool {
+        false
+    }
+
+    fn remove(&self, _key: u16) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        let v = Viqr;
+        assert_eq!(v.mark(keys::QUOTE), Some(1));
+        assert_eq!(v.mark(keys::with_shift(keys::SLASH, true)), Some(3));
+        assert_eq!(v.mark(keys::SLASH), None);
+        assert_eq!(v.mark(keys::DOT), Some(5));
+    }
+
+    #[test]
+    fn test_tones() {
+        let v = Viqr;
+        assert_eq!(
+            v.tone(keys::with_shift(keys::N6, true)),
+            Some(ToneType::Circumflex)
+        );
+        assert_eq!(
+            v.tone(keys::with_shift(keys::EQUAL, true)),
+            Some(ToneType::Horn)
+        );
+        assert_eq!(
+            v.tone(keys::with_shift(keys::N9, true)),
+            Some(ToneType::Breve)
+        );
+        assert_eq!(v.tone(keys::N6), None);
+    }
+}
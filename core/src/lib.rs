@@ -1,13 +1,13 @@
 //! Gõ Nhanh Vietnamese IME Core
 //!
-//! Simple Vietnamese input method engine supporting Telex and VNI.
+//! Simple Vietnamese input method engine supporting Telex, VNI and VIQR.
 //!
 //! # FFI Usage
 //!
 //! ```c
 //! // Initialize once at app start
 //! ime_init();
-//! ime_method(0);  // 0=Telex, 1=VNI
+//! ime_method(0);  // 0=Telex, 1=VNI, 2=VIQR
 //!
 //! // Process each keystroke
 //! ImeResult* r = ime_key(keycode, is_shift, is_ctrl);
@@ -19,17 +19,35 @@
 //! // Clean up on word boundary
 //! ime_clear();
 //! ```
+//!
+//! Hosts with several input contexts own one engine per context instead:
+//!
+//! ```c
+//! Engine* h = ime_engine_new();
+//! ime_engine_method(h, 0);
+//! ImeResult* r = ime_engine_key_ext(h, keycode, caps, ctrl, shift);
+//! ime_free(r);
+//! ime_engine_free(h);
+//! ```
 
+pub mod convert;
 pub mod data;
 pub mod engine;
+pub mod fold;
 pub mod input;
+pub mod lines;
+pub mod spell;
 pub mod updater;
 pub mod utils;
 
-use engine::{Engine, Result};
+use engine::shortcut::InputPurpose;
+use engine::{Edit, Engine, Result};
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use std::sync::Mutex;
 
 // Global engine instance (thread-safe via Mutex)
+// Backs the legacy `ime_*` API; `ime_engine_*` functions use caller-owned handles.
 static ENGINE: Mutex<Option<Engine>> = Mutex::new(None);
 
 /// Lock the engine mutex, recovering from poisoned state if needed (for tests)
@@ -37,6 +55,145 @@ fn lock_engine() -> std::sync::MutexGuard<'static, Option<Engine>> {
     ENGINE.lock().unwrap_or_else(|e| e.into_inner())
 }
 
+/// Run `f` against the default engine. Returns None if not initialized.
+fn with_engine<R>(f: impl FnOnce(&mut Engine) -> R) -> Option<R> {
+    lock_engine().as_mut().map(f)
+}
+
+/// Run `f` against an engine handle. Returns None for null handles.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+unsafe fn with_handle<R>(handle: *mut Engine, f: impl FnOnce(&mut Engine) -> R) -> Option<R> {
+    handle.as_mut().map(f)
+}
+
+/// Borrow a C string as UTF-8. Returns None for null or invalid UTF-8.
+///
+/// # Safety
+/// `s` must be null or a valid null-terminated string.
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// Move a result to the heap for FFI (freed with `ime_free`)
+fn into_ffi(r: Result) -> *mut Result {
+    Box::into_raw(Box::new(r))
+}
+
+/// Add a shortcut, auto-detecting its trigger type (see `Shortcut::auto`)
+fn add_shortcut(e: &mut Engine, trigger: &str, replacement: &str) {
+    e.shortcuts_mut()
+        .add(engine::shortcut::Shortcut::auto(trigger, replacement));
+}
+
+/// Import shortcut file contents, mapping errors to FFI codes
+fn import_shortcuts(e: &mut Engine, text: &str) -> i32 {
+    match e.shortcuts_mut().import(text) {
+        Ok(_) => 0,
+        Err(err) => err.line as i32,
+    }
+}
+
+/// Write `text` NUL-terminated to `out` if it fits, returning its byte length.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+unsafe fn copy_text(text: &str, out: *mut c_char, max_len: i64) -> i64 {
+    if !out.is_null() && (text.len() as i64) < max_len {
+        std::ptr::copy_nonoverlapping(text.as_ptr(), out as *mut u8, text.len());
+        *out.add(text.len()) = 0;
+    }
+    text.len() as i64
+}
+
+/// Parse a method table and install it, mapping errors to FFI codes
+fn load_method(e: &mut Engine, definition: &str) -> i32 {
+    match input::TableMethod::parse(definition) {
+        Ok(table) => {
+            e.load_method(table);
+            0
+        }
+        Err(err) if err.line > 0 => err.line as i32,
+        Err(_) => -1,
+    }
+}
+
+/// Copy the composed buffer as UTF-32 into `out`, returning codepoints written.
+///
+/// # Safety
+/// `out` must point to valid memory of at least `max_len * sizeof(u32)` bytes.
+unsafe fn copy_buffer(e: &Engine, out: *mut u32, max_len: i64) -> i64 {
+    let full = data::charset::encode(&e.get_buffer_string(), e.output_charset());
+    let utf32: Vec<u32> = full.chars().map(|c| c as u32).collect();
+    let len = utf32.len().min(max_len as usize);
+    std::ptr::copy_nonoverlapping(utf32.as_ptr(), out, len);
+    len as i64
+}
+
+/// Parse a config file and apply it, mapping errors to FFI codes
+fn load_config(e: &mut Engine, text: &str) -> i32 {
+    match engine::config::EngineConfig::parse(text) {
+        Ok(config) => {
+            e.set_global_config(&config);
+            0
+        }
+        Err(err) => err.line as i32,
+    }
+}
+
+fn load_policy(e: &mut Engine, text: &str) -> i32 {
+    match engine::policy::AppPolicy::parse(text) {
+        Ok(policy) => {
+            e.set_policy(policy);
+            0
+        }
+        Err(err) => err.line as i32,
+    }
+}
+
+/// Store one per-app option; 0 on success, -1 if invalid.
+fn set_policy(e: &mut Engine, pattern: &str, key: &str, value: &str) -> i32 {
+    if e.policy_mut().set(pattern, key, value).is_err() {
+        return -1;
+    }
+    e.set_policy(e.policy().clone());
+    0
+}
+
+/// Write the engine snapshot to `out` if it fits, returning its size.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+unsafe fn copy_snapshot(e: &Engine, out: *mut u8, max_len: i64) -> i64 {
+    let blob = e.snapshot().to_bytes();
+    if !out.is_null() && blob.len() as i64 <= max_len {
+        std::ptr::copy_nonoverlapping(blob.as_ptr(), out, blob.len());
+    }
+    blob.len() as i64
+}
+
+/// Restore an engine from a snapshot blob, leaving it unchanged on bad data.
+///
+/// # Safety
+/// `data` must be null or point to valid memory of at least `len` bytes.
+unsafe fn restore_snapshot(e: &mut Engine, data: *const u8, len: i64) -> bool {
+    if data.is_null() || len < 0 {
+        return false;
+    }
+    let blob = std::slice::from_raw_parts(data, len as usize);
+    match engine::state::EngineState::from_bytes(blob) {
+        Some(state) => {
+            e.restore(state);
+            true
+        }
+        None => false,
+    }
+}
+
 // ============================================================
 // FFI Interface
 // ============================================================
@@ -76,13 +233,7 @@ pub extern "C" fn ime_init() {
 /// use `ime_key_ext` with the shift parameter.
 #[no_mangle]
 pub extern "C" fn ime_key(key: u16, caps: bool, ctrl: bool) -> *mut Result {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        let r = e.on_key(key, caps, ctrl);
-        Box::into_raw(Box::new(r))
-    } else {
-        std::ptr::null_mut()
-    }
+    with_engine(|e| into_ffi(e.on_key(key, caps, ctrl))).unwrap_or(std::ptr::null_mut())
 }
 
 /// Process a key event with extended parameters.
@@ -105,27 +256,93 @@ pub extern "C" fn ime_key(key: u16, caps: bool, ctrl: bool) -> *mut Result {
 /// - etc.
 #[no_mangle]
 pub extern "C" fn ime_key_ext(key: u16, caps: bool, ctrl: bool, shift: bool) -> *mut Result {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        let r = e.on_key_ext(key, caps, ctrl, shift);
-        Box::into_raw(Box::new(r))
-    } else {
-        std::ptr::null_mut()
-    }
+    with_engine(|e| into_ffi(e.on_key_ext(key, caps, ctrl, shift))).unwrap_or(std::ptr::null_mut())
+}
+
+/// Process a character already resolved by the host keyboard layout.
+///
+/// Layout-independent alternative to `ime_key_ext` for hosts whose native
+/// keycodes don't map to a US layout (AZERTY, Dvorak, Colemak).
+///
+/// # Arguments
+/// * `ch` - Unicode codepoint produced by the key (8 = backspace, 27 = ESC)
+/// * `modifiers` - bit 0 (0x01): Cmd/Ctrl/Alt is pressed (bypasses IME)
+///
+/// # Returns
+/// * Pointer to `Result` struct (caller must free with `ime_free`)
+/// * `null` if engine not initialized
+#[no_mangle]
+pub extern "C" fn ime_char(ch: u32, modifiers: u8) -> *mut Result {
+    let ch = char::from_u32(ch).unwrap_or(char::REPLACEMENT_CHARACTER);
+    with_engine(|e| into_ffi(e.on_char(ch, modifiers))).unwrap_or(std::ptr::null_mut())
+}
+
+/// Undo the latest IME action.
+///
+/// Reverts one step: a mark, tone, stroke or W-as-vowel inside the current
+/// word (keys typed since then stay as plain letters), or a shortcut
+/// expansion / auto-restore right after it happened. Bind it to e.g.
+/// Ctrl+Z while composing, or offer it after an expansion.
+///
+/// # Returns
+/// * Pointer to `Result` (free with `ime_free`), `action` 0 if nothing to undo
+/// * `null` if engine not initialized
+#[no_mangle]
+pub extern "C" fn ime_undo() -> *mut Result {
+    with_engine(|e| into_ffi(e.undo())).unwrap_or(std::ptr::null_mut())
 }
 
 /// Set the input method.
 ///
 /// # Arguments
-/// * `method` - 0 for Telex, 1 for VNI
+/// * `method` - 0 for Telex, 1 for VNI, 2 for VIQR, 255 for the loaded table
 ///
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_method(method: u8) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_method(method);
-    }
+    with_engine(|e| e.set_method(method));
+}
+
+/// Load a user-defined input method table and switch to it.
+///
+/// See `input::table` for the definition format. The table stays
+/// loaded, so `ime_method(255)` re-activates it after switching away.
+///
+/// # Arguments
+/// * `definition` - C string with the method table
+///
+/// # Returns
+/// * `0` on success
+/// * 1-based line number of the first syntax error
+/// * `-1` if the string is null/invalid, the table has no modifier keys,
+///   or the engine is not initialized
+///
+/// # Safety
+/// Pointer must be a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_method_load(definition: *const c_char) -> i32 {
+    let Some(def) = c_str(definition) else {
+        return -1;
+    };
+    with_engine(|e| load_method(e, def)).unwrap_or(-1)
+}
+
+/// Set the output charset for composed text.
+///
+/// Legacy charsets emit each byte as the Latin-1 codepoint of the same value;
+/// `backspace` counts code units in the selected charset.
+///
+/// # Arguments
+/// * `charset` - 0=Unicode (default), 1=VNI-Windows, 2=TCVN3 (ABC), 3=VIQR,
+///   4=Unicode NFD (base letter + combining marks)
+///
+/// No-op if engine not initialized or charset is unknown.
+#[no_mangle]
+pub extern "C" fn ime_output_charset(charset: u8) {
+    let Some(charset) = data::charset::Charset::from_u8(charset) else {
+        return;
+    };
+    with_engine(|e| e.set_output_charset(charset));
 }
 
 /// Enable or disable the engine.
@@ -134,10 +351,7 @@ pub extern "C" fn ime_method(method: u8) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_enabled(enabled: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_enabled(enabled);
-    }
+    with_engine(|e| e.set_enabled(enabled));
 }
 
 /// Set whether to skip w→ư shortcut in Telex mode.
@@ -147,10 +361,7 @@ pub extern "C" fn ime_enabled(enabled: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_skip_w_shortcut(skip: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_skip_w_shortcut(skip);
-    }
+    with_engine(|e| e.set_skip_w_shortcut(skip));
 }
 
 /// Set whether ESC key restores raw ASCII input.
@@ -160,10 +371,7 @@ pub extern "C" fn ime_skip_w_shortcut(skip: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_esc_restore(enabled: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_esc_restore(enabled);
-    }
+    with_engine(|e| e.set_esc_restore(enabled));
 }
 
 /// Set whether to enable free tone placement (skip validation).
@@ -174,10 +382,7 @@ pub extern "C" fn ime_esc_restore(enabled: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_free_tone(enabled: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_free_tone(enabled);
-    }
+    with_engine(|e| e.set_free_tone(enabled));
 }
 
 /// Set whether to use modern orthography for tone placement.
@@ -187,10 +392,7 @@ pub extern "C" fn ime_free_tone(enabled: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_modern(modern: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_modern_tone(modern);
-    }
+    with_engine(|e| e.set_modern_tone(modern));
 }
 
 /// Enable/disable English auto-restore (experimental feature).
@@ -201,10 +403,72 @@ pub extern "C" fn ime_modern(modern: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_english_auto_restore(enabled: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_english_auto_restore(enabled);
-    }
+    with_engine(|e| e.set_english_auto_restore(enabled));
+}
+
+/// Enable/disable the bundled English word list for auto-restore.
+///
+/// When `enabled` is true, Vietnamese words in use ("sax" → "sã") are
+/// only restored if their keys spell common English.
+/// Words auto-restore keeps as Vietnamese ("mix" → "mĩ") are not affected.
+/// No-op if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_english_dictionary(enabled: bool) {
+    with_engine(|e| e.set_english_dictionary(enabled));
+}
+
+/// Enable/disable strict syllables.
+///
+/// When `enabled` is true, a tone, mark or stroke that can't lead to a
+/// Vietnamese syllable in use is typed as a plain letter ("quuyns" stays).
+/// No-op if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_strict_syllables(enabled: bool) {
+    with_engine(|e| e.set_strict_syllables(enabled));
+}
+
+/// Enable/disable auto-accent suggestions.
+///
+/// When `enabled` is true, a word typed without diacritics and committed
+/// with space gets toned candidates ("toi" → "tôi", "tối", ...), read with
+/// `ime_suggestions`. No-op if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_auto_accent(enabled: bool) {
+    with_engine(|e| e.set_auto_accent(enabled));
+}
+
+/// Enable/disable caret tracking inside the word.
+///
+/// When `enabled` is true, Left/Right arrows move a caret inside the word
+/// and keys compose around it. Only enable it in hosts that no longer
+/// clear on arrows and apply `ime_key_v2` edits. No-op if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_caret_tracking(enabled: bool) {
+    with_engine(|e| e.set_caret_tracking(enabled));
+}
+
+/// Add a word that auto-restore always restores (e.g., "mix", not "mĩ").
+///
+/// # Safety
+/// Pointer must be a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_add_english_word(word: *const c_char) {
+    let Some(word_str) = c_str(word) else {
+        return;
+    };
+    with_engine(|e| e.add_english_word(word_str));
+}
+
+/// Remove a word added with `ime_add_english_word`.
+///
+/// # Safety
+/// Pointer must be a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_remove_english_word(word: *const c_char) {
+    let Some(word_str) = c_str(word) else {
+        return;
+    };
+    with_engine(|e| e.remove_english_word(word_str));
 }
 
 /// Enable/disable auto-capitalize after sentence-ending punctuation.
@@ -215,10 +479,7 @@ pub extern "C" fn ime_english_auto_restore(enabled: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_auto_capitalize(enabled: bool) {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.set_auto_capitalize(enabled);
-    }
+    with_engine(|e| e.set_auto_capitalize(enabled));
 }
 
 /// Clear the input buffer.
@@ -228,23 +489,19 @@ pub extern "C" fn ime_auto_capitalize(enabled: bool) {
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_clear() {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.clear();
-    }
+    with_engine(|e| e.clear());
 }
 
 /// Clear everything including word history.
 ///
-/// Call when cursor position changes (mouse click, arrow keys, focus change).
+/// Call when cursor position changes (mouse click, Up/Down, focus change).
 /// This prevents accidental restore from stale history.
+/// Left/Right can go through `ime_key` instead: they move the caret inside
+/// the current word so tones can still be fixed there.
 /// No-op if engine not initialized.
 #[no_mangle]
 pub extern "C" fn ime_clear_all() {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.clear_all();
-    }
+    with_engine(|e| e.clear_all());
 }
 
 /// Get the full composed buffer as UTF-32 codepoints.
@@ -266,17 +523,7 @@ pub unsafe extern "C" fn ime_get_buffer(out: *mut u32, max_len: i64) -> i64 {
     if out.is_null() || max_len <= 0 {
         return 0;
     }
-
-    let guard = lock_engine();
-    if let Some(ref e) = *guard {
-        let full = e.get_buffer_string();
-        let utf32: Vec<u32> = full.chars().map(|c| c as u32).collect();
-        let len = utf32.len().min(max_len as usize);
-        std::ptr::copy_nonoverlapping(utf32.as_ptr(), out, len);
-        len as i64
-    } else {
-        0
-    }
+    with_engine(|e| copy_buffer(e, out, max_len)).unwrap_or(0)
 }
 
 /// Free a result pointer returned by `ime_key`.
@@ -292,6 +539,97 @@ pub unsafe extern "C" fn ime_free(r: *mut Result) {
     }
 }
 
+// ============================================================
+// Variable-length Result FFI
+// ============================================================
+
+/// Variable-length key result, not capped at MAX codepoints.
+///
+/// Describes one edit: delete `backspace` chars before the caret and
+/// `delete_after` chars after it, insert `text`, then optionally place the
+/// caret at `cursor`. Freed with `ime_free_v2`.
+#[repr(C)]
+pub struct ResultV2 {
+    /// 0=None (pass through), 1=Send (replace text), 2=Restore
+    pub action: u8,
+    /// Same bits as `Result.flags` (0x01 = key consumed)
+    pub flags: u8,
+    /// Number of characters to delete before inserting
+    pub backspace: u32,
+    /// Number of characters to delete after the caret (edits inside a word)
+    pub delete_after: u32,
+    /// Insert text as UTF-8, NUL-terminated
+    pub text: *mut u8,
+    /// Byte length of `text` (excluding the NUL)
+    pub text_len: u32,
+    /// Insert text as UTF-32 codepoints
+    pub chars: *mut u32,
+    /// Number of codepoints in `chars`
+    pub chars_len: u32,
+    /// Caret position inside the inserted text in codepoints, -1 = after it
+    pub cursor: i32,
+}
+
+/// Move an edit to the heap for FFI (freed with `ime_free_v2`)
+fn into_ffi_v2(edit: Edit) -> *mut ResultV2 {
+    let mut utf8 = edit.text.clone().into_bytes();
+    let text_len = utf8.len() as u32;
+    utf8.push(0);
+    let utf32: Vec<u32> = edit.text.chars().map(|c| c as u32).collect();
+    let chars_len = utf32.len() as u32;
+
+    Box::into_raw(Box::new(ResultV2 {
+        action: edit.action,
+        flags: if edit.key_consumed {
+            engine::FLAG_KEY_CONSUMED
+        } else {
+            0
+        },
+        backspace: edit.backspace as u32,
+        delete_after: edit.delete_after as u32,
+        text: Box::into_raw(utf8.into_boxed_slice()) as *mut u8,
+        text_len,
+        chars: Box::into_raw(utf32.into_boxed_slice()) as *mut u32,
+        chars_len,
+        cursor: edit.cursor.map_or(-1, |c| c as i32),
+    }))
+}
+
+/// Process a key event and return a variable-length result.
+///
+/// Same arguments as `ime_key_ext`. Use this when shortcut expansions may
+/// exceed the 64 codepoints of `Result`; `ime_key`/`ime_key_ext` stay available.
+///
+/// # Returns
+/// * Pointer to `ResultV2` (caller must free with `ime_free_v2`)
+/// * `null` if engine not initialized
+#[no_mangle]
+pub extern "C" fn ime_key_v2(key: u16, caps: bool, ctrl: bool, shift: bool) -> *mut ResultV2 {
+    with_engine(|e| into_ffi_v2(e.on_key_edit(key, caps, ctrl, shift)))
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Free a result returned by `ime_key_v2` or `ime_engine_key_v2`.
+///
+/// # Safety
+/// * `r` must be a pointer returned by `ime_key_v2`/`ime_engine_key_v2`, or null
+/// * Must be called exactly once per non-null result
+#[no_mangle]
+pub unsafe extern "C" fn ime_free_v2(r: *mut ResultV2) {
+    if r.is_null() {
+        return;
+    }
+    let r = Box::from_raw(r);
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+        r.text,
+        r.text_len as usize + 1,
+    )));
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+        r.chars,
+        r.chars_len as usize,
+    )));
+}
+
 // ============================================================
 // Shortcut FFI
 // ============================================================
@@ -305,36 +643,11 @@ pub unsafe extern "C" fn ime_free(r: *mut Result) {
 /// # Safety
 /// Both pointers must be valid null-terminated UTF-8 strings.
 #[no_mangle]
-pub unsafe extern "C" fn ime_add_shortcut(
-    trigger: *const std::os::raw::c_char,
-    replacement: *const std::os::raw::c_char,
-) {
-    if trigger.is_null() || replacement.is_null() {
+pub unsafe extern "C" fn ime_add_shortcut(trigger: *const c_char, replacement: *const c_char) {
+    let (Some(trigger_str), Some(replacement_str)) = (c_str(trigger), c_str(replacement)) else {
         return;
-    }
-
-    let trigger_str = match std::ffi::CStr::from_ptr(trigger).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
     };
-    let replacement_str = match std::ffi::CStr::from_ptr(replacement).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
-    };
-
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        // Auto-detect shortcut type:
-        // - If trigger contains only non-letter chars (like "->", "=>"), use immediate trigger
-        // - Otherwise use word boundary trigger (traditional abbreviations like "vn" → "Việt Nam")
-        let is_symbol_trigger = trigger_str.chars().all(|c| !c.is_alphabetic());
-        let shortcut = if is_symbol_trigger {
-            engine::shortcut::Shortcut::immediate(trigger_str, replacement_str)
-        } else {
-            engine::shortcut::Shortcut::new(trigger_str, replacement_str)
-        };
-        e.shortcuts_mut().add(shortcut);
-    }
+    with_engine(|e| add_shortcut(e, trigger_str, replacement_str));
 }
 
 /// Remove a shortcut from the engine.
@@ -345,29 +658,99 @@ pub unsafe extern "C" fn ime_add_shortcut(
 /// # Safety
 /// Pointer must be a valid null-terminated UTF-8 string.
 #[no_mangle]
-pub unsafe extern "C" fn ime_remove_shortcut(trigger: *const std::os::raw::c_char) {
-    if trigger.is_null() {
+pub unsafe extern "C" fn ime_remove_shortcut(trigger: *const c_char) {
+    let Some(trigger_str) = c_str(trigger) else {
         return;
-    }
+    };
+    with_engine(|e| e.shortcuts_mut().remove(trigger_str));
+}
 
-    let trigger_str = match std::ffi::CStr::from_ptr(trigger).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
+/// Import shortcuts from file contents, adding them to the table.
+///
+/// Reads the native format (see `engine::shortcut`) or plain UniKey macro
+/// files (`trigger:replacement` lines, ';' comments).
+///
+/// # Arguments
+/// * `text` - Contents of a shortcut file (never read as a path)
+///
+/// # Returns
+/// * 0 on success
+/// * Line number (>0) of the first invalid line; nothing is imported
+/// * -1 if the argument is null/not UTF-8, or engine not initialized
+///
+/// # Safety
+/// `text` must be null or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_shortcuts_import_text(text: *const c_char) -> i32 {
+    let Some(text) = c_str(text) else {
+        return -1;
     };
+    with_engine(|e| import_shortcuts(e, text)).unwrap_or(-1)
+}
 
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.shortcuts_mut().remove(trigger_str);
-    }
+/// Import shortcuts from a file on disk. See `ime_shortcuts_import_text`.
+///
+/// # Arguments
+/// * `path` - Path of the shortcut file
+///
+/// # Returns
+/// Same as `ime_shortcuts_import_text`; -1 also when the file can't be read.
+///
+/// # Safety
+/// `path` must be null or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_shortcuts_import_file(path: *const c_char) -> i32 {
+    let Some(text) = c_str(path).and_then(|p| std::fs::read_to_string(p).ok()) else {
+        return -1;
+    };
+    with_engine(|e| import_shortcuts(e, &text)).unwrap_or(-1)
+}
+
+/// Export all shortcuts in the native file format.
+///
+/// # Arguments
+/// * `out` - Buffer for the UTF-8 text (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Text length in bytes without the NUL. Written only when it fits.
+/// 0 if engine not initialized.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_shortcuts_export(out: *mut c_char, max_len: i64) -> i64 {
+    with_engine(|e| copy_text(&e.shortcuts().export(), out, max_len)).unwrap_or(0)
+}
+
+/// Set the text inserted by `{clipboard}` in shortcut templates.
+///
+/// The host updates this slot (e.g., on copy) since the core can't read
+/// the system clipboard.
+///
+/// # Safety
+/// `text` must be null or a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_clipboard(text: *const c_char) {
+    let Some(text) = c_str(text) else {
+        return;
+    };
+    with_engine(|e| e.shortcuts_mut().set_clipboard(text));
+}
+
+/// Set the local time offset used by `{date}` and `{time}` in shortcut templates.
+///
+/// # Arguments
+/// * `minutes` - Offset from UTC in minutes (e.g., 420 for UTC+7)
+#[no_mangle]
+pub extern "C" fn ime_set_utc_offset(minutes: i32) {
+    with_engine(|e| e.shortcuts_mut().set_utc_offset_minutes(minutes));
 }
 
 /// Clear all shortcuts from the engine.
 #[no_mangle]
 pub extern "C" fn ime_clear_shortcuts() {
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.shortcuts_mut().clear();
-    }
+    with_engine(|e| e.shortcuts_mut().clear());
 }
 
 // ============================================================
@@ -386,281 +769,1996 @@ pub extern "C" fn ime_clear_shortcuts() {
 /// # Safety
 /// Pointer must be a valid null-terminated UTF-8 string.
 #[no_mangle]
-pub unsafe extern "C" fn ime_restore_word(word: *const std::os::raw::c_char) {
-    if word.is_null() {
+pub unsafe extern "C" fn ime_restore_word(word: *const c_char) {
+    let Some(word_str) = c_str(word) else {
         return;
-    }
-    let word_str = match std::ffi::CStr::from_ptr(word).to_str() {
-        Ok(s) => s,
-        Err(_) => return,
     };
-    let mut guard = lock_engine();
-    if let Some(ref mut e) = *guard {
-        e.restore_word(word_str);
-    }
+    with_engine(|e| e.restore_word(word_str));
 }
 
-// ============================================================
-// Tests
-// ============================================================
+/// Re-enter composition from the text around the caret.
+///
+/// For hosts exposing surrounding text (IBus, Fcitx): call after focus or
+/// caret changes instead of `ime_clear_all`. The word at the cursor becomes
+/// editable again, backspace after "word " re-enters it, and text ending a
+/// sentence (". ") arms auto-capitalize.
+///
+/// # Arguments
+/// * `text` - text around the caret (UTF-8, e.g. the current line)
+/// * `cursor` - caret position in `text`, in Unicode codepoints
+///
+/// # Safety
+/// `text` must be a valid null-terminated UTF-8 string or null (no-op).
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_surrounding_text(text: *const c_char, cursor: u32) {
+    let Some(text) = c_str(text) else {
+        return;
+    };
+    with_engine(|e| e.set_surrounding_text(text, cursor as usize));
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::data::keys;
-    use serial_test::serial;
+/// Set the focused app and field type.
+///
+/// Call on focus changes. Shortcuts scoped to an app or field type only
+/// expand there; terminal fields get no Vietnamese transforms and password
+/// fields pass every key through. Resets the current word.
+///
+/// # Arguments
+/// * `app_id` - App identifier (e.g., bundle ID "com.apple.mail"), null if unknown
+/// * `purpose` - 0=text, 1=terminal, 2=password, 3=url, 4=code
+///
+/// # Safety
+/// `app_id` must be null or a valid null-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_set_context(app_id: *const c_char, purpose: u8) {
+    let app_id = c_str(app_id).unwrap_or("");
+    with_engine(|e| e.set_context(app_id, InputPurpose::from_u8(purpose)));
+}
+
+// ============================================================
+// Suggestion FFI
+// ============================================================
+
+/// Get the auto-accent candidates for the word just committed.
+///
+/// Best first, one per line. They stay available until the next key.
+///
+/// # Arguments
+/// * `out` - Buffer for the UTF-8 text (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Text length in bytes without the NUL (0 when there are none).
+/// Written only when it fits. 0 if engine not initialized.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_suggestions(out: *mut c_char, max_len: i64) -> i64 {
+    with_engine(|e| copy_text(&e.suggestions().join("\n"), out, max_len)).unwrap_or(0)
+}
+
+/// Replace the word just committed with one of its candidates.
+///
+/// # Arguments
+/// * `index` - Line of the candidate in `ime_suggestions`
+///
+/// # Returns
+/// Pointer to Result (caller must free with `ime_free`): the word and its
+/// space are deleted and the candidate plus space sent. Action::None for
+/// an unknown index. Null if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_accept_suggestion(index: u32) -> *mut Result {
+    with_engine(|e| into_ffi(e.accept_suggestion(index as usize))).unwrap_or(std::ptr::null_mut())
+}
+
+// ============================================================
+// Config FFI
+// ============================================================
+
+/// Load all options from a config file.
+///
+/// One format for every platform (see `engine::config`):
+/// `key = value` lines with `version`, `method`, option flags and
+/// `output_charset`. Missing keys take their defaults.
+///
+/// # Returns
+/// * 0 on success
+/// * Line number (>0) of the first error, e.g. an unknown key; nothing is applied
+/// * -1 if `text` is null or not UTF-8, or engine not initialized
+///
+/// # Safety
+/// `text` must be null or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_config_load(text: *const c_char) -> i32 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    with_engine(|e| load_config(e, text)).unwrap_or(-1)
+}
+
+/// Write the current options as a config file.
+///
+/// Per-app overrides (see `ime_policy_load`) are not included.
+///
+/// # Arguments
+/// * `out` - Buffer for the UTF-8 text (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Text length in bytes without the NUL. Written only when it fits.
+/// 0 if engine not initialized.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_config_dump(out: *mut c_char, max_len: i64) -> i64 {
+    with_engine(|e| copy_text(&e.global_config().to_string(), out, max_len)).unwrap_or(0)
+}
+
+// ============================================================
+// App Policy FFI
+// ============================================================
+
+/// Load per-app option overrides.
+///
+/// `[app-id glob]` sections of config keys (see `engine::policy`), applied
+/// on every `ime_set_context`. Replaces the previous policy and applies it
+/// to the focused app right away.
+///
+/// # Returns
+/// * 0 on success
+/// * Line number (>0) of the first error; nothing is applied
+/// * -1 if `text` is null or not UTF-8, or engine not initialized
+///
+/// # Safety
+/// `text` must be null or a valid null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ime_policy_load(text: *const c_char) -> i32 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    with_engine(|e| load_policy(e, text)).unwrap_or(-1)
+}
+
+/// Write the per-app policy in the format read by `ime_policy_load`.
+///
+/// # Arguments
+/// * `out` - Buffer for the UTF-8 text (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Text length in bytes without the NUL. Written only when it fits.
+/// 0 if engine not initialized.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_policy_dump(out: *mut c_char, max_len: i64) -> i64 {
+    with_engine(|e| copy_text(&e.policy().to_string(), out, max_len)).unwrap_or(0)
+}
+
+/// Remember one option for an app.
+///
+/// E.g. when the user turns Vietnamese off while in Terminal:
+/// `ime_policy_set("com.apple.Terminal", "enabled", "false")`.
+///
+/// # Arguments
+/// * `pattern` - App ID or glob (`*`, `?`)
+/// * `key`, `value` - Config key and value, as in `ime_config_load`
+///
+/// # Returns
+/// 0 on success, -1 for an unknown key, invalid value or null argument.
+///
+/// # Safety
+/// All pointers must be null or valid null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn ime_policy_set(
+    pattern: *const c_char,
+    key: *const c_char,
+    value: *const c_char,
+) -> i32 {
+    let (Some(pattern), Some(key), Some(value)) = (c_str(pattern), c_str(key), c_str(value)) else {
+        return -1;
+    };
+    with_engine(|e| set_policy(e, pattern, key, value)).unwrap_or(-1)
+}
+
+// ============================================================
+// Snapshot FFI
+// ============================================================
+
+/// Serialize the engine state for the current app.
+///
+/// Covers the input method, options, the half-typed word and word history,
+/// so hosts can keep one blob per app ID and swap it on focus change.
+/// Shortcuts and loaded method tables are shared and not included.
+///
+/// # Arguments
+/// * `out` - Buffer for the blob (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes
+///
+/// # Returns
+/// Blob size in bytes. The blob is only written when it fits in `max_len`.
+/// 0 if engine not initialized.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_snapshot(out: *mut u8, max_len: i64) -> i64 {
+    with_engine(|e| copy_snapshot(e, out, max_len)).unwrap_or(0)
+}
+
+/// Restore engine state from a blob written by `ime_snapshot`.
+///
+/// # Returns
+/// true on success. false for null, truncated or other-version blobs;
+/// the engine is left unchanged then.
+///
+/// # Safety
+/// `data` must be null or point to valid memory of at least `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_restore(data: *const u8, len: i64) -> bool {
+    with_engine(|e| restore_snapshot(e, data, len)).unwrap_or(false)
+}
+
+// ============================================================
+// Key Log FFI
+// ============================================================
+
+/// Start or stop recording a key log.
+///
+/// While recording, keys, undos, clears and option changes are logged
+/// with their results (see `engine::record`), so a user can attach the
+/// session to a bug report. Starting drops any previous log.
+/// No-op if engine not initialized.
+#[no_mangle]
+pub extern "C" fn ime_record(enabled: bool) {
+    with_engine(|e| {
+        if enabled {
+            e.start_recording();
+        } else {
+            e.stop_recording();
+        }
+    });
+}
+
+/// Get the key log recorded so far.
+///
+/// # Arguments
+/// * `out` - Buffer for the UTF-8 log (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Log length in bytes without the NUL. Written only when it fits.
+/// -1 if not recording or engine not initialized.
+///
+/// # Safety
+/// `out` must be null or point to valid memory of at least `max_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ime_recording(out: *mut c_char, max_len: i64) -> i64 {
+    with_engine(|e| e.recording().map_or(-1, |log| copy_text(log, out, max_len))).unwrap_or(-1)
+}
+
+// ============================================================
+// Text FFI
+// ============================================================
+
+/// Rewrite tone placement in `text` into one orthography.
+///
+/// Also fixes misplaced marks ("hóa" → "hoá", "qủa" → "quả"). Works on
+/// precomposed text without touching the engine.
+///
+/// # Arguments
+/// * `text` - UTF-8 text to normalize
+/// * `style` - 0=traditional (hòa, thúy), 1=modern (hoà, thuý)
+/// * `out` - Buffer for the UTF-8 result (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Result length in bytes without the NUL. Written only when it fits.
+/// -1 if `text` is null or not UTF-8.
+///
+/// # Safety
+/// * `text` must be null or a valid null-terminated string
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_normalize_text(
+    text: *const c_char,
+    style: u8,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    copy_text(&spell::normalize(text, style != 0), out, max_len)
+}
+
+/// Convert raw keystroke text ("tieengs Vieetj") to Vietnamese.
+///
+/// Runs `text` through a throwaway engine with default options and
+/// `method`; the default instance is not touched.
+///
+/// # Arguments
+/// * `text` - UTF-8 keystroke text
+/// * `method` - 0=Telex, 1=VNI, 2=VIQR
+/// * `out` - Buffer for the UTF-8 result (may be null to query the size)
+/// * `max_len` - Size of `out` in bytes, including the NUL terminator
+///
+/// # Returns
+/// Result length in bytes without the NUL. Written only when it fits.
+/// -1 if `text` is null or not UTF-8.
+///
+/// # Safety
+/// * `text` must be null or a valid null-terminated string
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_compose_text(
+    text: *const c_char,
+    method: u8,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    let config = engine::config::EngineConfig {
+        method,
+        ..Default::default()
+    };
+    copy_text(&convert::compose(text, &config), out, max_len)
+}
+
+/// Convert Vietnamese text to the shortest Telex or VNI keystrokes.
+///
+/// # Arguments
+/// * `text` - UTF-8 composed text
+/// * `method` - 0=Telex, 1=VNI
+/// * `out`, `max_len` - As `ime_compose_text`
+///
+/// # Returns
+/// Result length in bytes without the NUL, or -1 if `text` is null or not
+/// UTF-8, or `method` is not Telex or VNI.
+///
+/// # Safety
+/// * `text` must be null or a valid null-terminated string
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_to_keystrokes(
+    text: *const c_char,
+    method: u8,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    match convert::to_keystrokes(text, method) {
+        Some(keystrokes) => copy_text(&keystrokes, out, max_len),
+        None => -1,
+    }
+}
+
+/// Fold text for search: diacritics stripped and lowercased.
+///
+/// "Đà Nẵng" → "da nang". Fold both the query and the searched text.
+///
+/// # Arguments
+/// * `text` - UTF-8 text
+/// * `out`, `max_len` - As `ime_compose_text`
+///
+/// # Returns
+/// Result length in bytes without the NUL, or -1 if `text` is null or not UTF-8.
+///
+/// # Safety
+/// * `text` must be null or a valid null-terminated string
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_fold_text(text: *const c_char, out: *mut c_char, max_len: i64) -> i64 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    copy_text(&fold::fold(text), out, max_len)
+}
+
+/// Find a query in text, ignoring diacritics and case.
+///
+/// For highlighting: the match is reported in `text` itself, so "ha noi"
+/// in "Phở Hà Nội" gives the range of "Hà Nội".
+///
+/// # Arguments
+/// * `text` - UTF-8 text to search
+/// * `query` - UTF-8 query (accented or not)
+/// * `start`, `end` - Receive the UTF-8 byte range of the match in `text`
+///
+/// # Returns
+/// true if found. false for no match, an empty query or null arguments.
+///
+/// # Safety
+/// * `text` and `query` must be null or valid null-terminated strings
+/// * `start` and `end` must be null or valid pointers
+#[no_mangle]
+pub unsafe extern "C" fn ime_fold_find(
+    text: *const c_char,
+    query: *const c_char,
+    start: *mut i64,
+    end: *mut i64,
+) -> bool {
+    let (Some(text), Some(query)) = (c_str(text), c_str(query)) else {
+        return false;
+    };
+    if start.is_null() || end.is_null() {
+        return false;
+    }
+    let Some(range) = fold::find(text, query) else {
+        return false;
+    };
+    *start = range.start as i64;
+    *end = range.end as i64;
+    true
+}
+
+// ============================================================
+// Multi-instance FFI (engine handles)
+// ============================================================
+//
+// Each handle owns an independent `Engine` (buffer, history, options,
+// shortcuts). Hosts with several input contexts (Fcitx/IBus) create one
+// handle per context so composition never leaks across windows.
+//
+// Handles are not internally locked: the host must not use the same
+// handle from two threads at once. Different handles are independent.
+
+/// Create a new engine instance.
+///
+/// # Returns
+/// Opaque handle, freed with `ime_engine_free`.
+#[no_mangle]
+pub extern "C" fn ime_engine_new() -> *mut Engine {
+    Box::into_raw(Box::new(Engine::new()))
+}
+
+/// Destroy an engine instance.
+///
+/// # Safety
+/// * `handle` must be a pointer returned by `ime_engine_new`, or null
+/// * Must be called exactly once per handle
+/// * Do not use `handle` after calling this function
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_free(handle: *mut Engine) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Process a key event on an engine instance.
+///
+/// Same semantics as `ime_key`. Returns null for a null handle.
+/// The result must be freed with `ime_free`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_key(
+    handle: *mut Engine,
+    key: u16,
+    caps: bool,
+    ctrl: bool,
+) -> *mut Result {
+    with_handle(handle, |e| into_ffi(e.on_key(key, caps, ctrl))).unwrap_or(std::ptr::null_mut())
+}
+
+/// Process a key event with shift state on an engine instance.
+///
+/// Same semantics as `ime_key_ext`. Returns null for a null handle.
+/// The result must be freed with `ime_free`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_key_ext(
+    handle: *mut Engine,
+    key: u16,
+    caps: bool,
+    ctrl: bool,
+    shift: bool,
+) -> *mut Result {
+    with_handle(handle, |e| into_ffi(e.on_key_ext(key, caps, ctrl, shift)))
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Process a layout-resolved character on an engine instance.
+///
+/// Same semantics as `ime_char`. Returns null for a null handle.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_char(
+    handle: *mut Engine,
+    ch: u32,
+    modifiers: u8,
+) -> *mut Result {
+    let ch = char::from_u32(ch).unwrap_or(char::REPLACEMENT_CHARACTER);
+    with_handle(handle, |e| into_ffi(e.on_char(ch, modifiers))).unwrap_or(std::ptr::null_mut())
+}
+
+/// Undo the latest IME action on an engine instance. See `ime_undo`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_undo(handle: *mut Engine) -> *mut Result {
+    with_handle(handle, |e| into_ffi(e.undo())).unwrap_or(std::ptr::null_mut())
+}
+
+/// Process a key event on an engine instance, variable-length result.
+///
+/// Same semantics as `ime_key_v2`. Returns null for a null handle.
+/// The result must be freed with `ime_free_v2`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_key_v2(
+    handle: *mut Engine,
+    key: u16,
+    caps: bool,
+    ctrl: bool,
+    shift: bool,
+) -> *mut ResultV2 {
+    with_handle(handle, |e| {
+        into_ffi_v2(e.on_key_edit(key, caps, ctrl, shift))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Set the input method of an engine instance (0=Telex, 1=VNI, 2=VIQR, 255=table).
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_method(handle: *mut Engine, method: u8) {
+    with_handle(handle, |e| e.set_method(method));
+}
+
+/// Load a method table into an engine instance. See `ime_method_load`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `definition` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_method_load(
+    handle: *mut Engine,
+    definition: *const c_char,
+) -> i32 {
+    let Some(def) = c_str(definition) else {
+        return -1;
+    };
+    with_handle(handle, |e| load_method(e, def)).unwrap_or(-1)
+}
+
+/// Set the output charset of an engine instance. See `ime_output_charset`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_output_charset(handle: *mut Engine, charset: u8) {
+    let Some(charset) = data::charset::Charset::from_u8(charset) else {
+        return;
+    };
+    with_handle(handle, |e| e.set_output_charset(charset));
+}
+
+/// Enable or disable an engine instance. See `ime_enabled`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_enabled(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_enabled(enabled));
+}
+
+/// Set w→ư shortcut skipping on an engine instance. See `ime_skip_w_shortcut`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_skip_w_shortcut(handle: *mut Engine, skip: bool) {
+    with_handle(handle, |e| e.set_skip_w_shortcut(skip));
+}
+
+/// Set ESC restore on an engine instance. See `ime_esc_restore`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_esc_restore(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_esc_restore(enabled));
+}
+
+/// Set free tone placement on an engine instance. See `ime_free_tone`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_free_tone(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_free_tone(enabled));
+}
+
+/// Set modern tone placement on an engine instance. See `ime_modern`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_modern(handle: *mut Engine, modern: bool) {
+    with_handle(handle, |e| e.set_modern_tone(modern));
+}
+
+/// Set English auto-restore on an engine instance. See `ime_english_auto_restore`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_english_auto_restore(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_english_auto_restore(enabled));
+}
+
+/// Set the English word list on an engine instance. See `ime_english_dictionary`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_english_dictionary(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_english_dictionary(enabled));
+}
+
+/// Set strict syllables on an engine instance. See `ime_strict_syllables`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_strict_syllables(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_strict_syllables(enabled));
+}
+
+/// Set auto-accent on an engine instance. See `ime_auto_accent`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_auto_accent(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_auto_accent(enabled));
+}
+
+/// Set caret tracking on an engine instance. See `ime_caret_tracking`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_caret_tracking(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_caret_tracking(enabled));
+}
+
+/// Add an English word to an engine instance. See `ime_add_english_word`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `word` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_add_english_word(handle: *mut Engine, word: *const c_char) {
+    let Some(word_str) = c_str(word) else {
+        return;
+    };
+    with_handle(handle, |e| e.add_english_word(word_str));
+}
+
+/// Remove an English word from an engine instance. See `ime_remove_english_word`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `word` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_remove_english_word(handle: *mut Engine, word: *const c_char) {
+    let Some(word_str) = c_str(word) else {
+        return;
+    };
+    with_handle(handle, |e| e.remove_english_word(word_str));
+}
+
+/// Set auto-capitalize on an engine instance. See `ime_auto_capitalize`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_auto_capitalize(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| e.set_auto_capitalize(enabled));
+}
+
+/// Clear the input buffer of an engine instance. See `ime_clear`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_clear(handle: *mut Engine) {
+    with_handle(handle, |e| e.clear());
+}
+
+/// Clear buffer and word history of an engine instance. See `ime_clear_all`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_clear_all(handle: *mut Engine) {
+    with_handle(handle, |e| e.clear_all());
+}
+
+/// Get the composed buffer of an engine instance. See `ime_get_buffer`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must point to valid memory of at least `max_len * sizeof(u32)` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_get_buffer(
+    handle: *mut Engine,
+    out: *mut u32,
+    max_len: i64,
+) -> i64 {
+    if out.is_null() || max_len <= 0 {
+        return 0;
+    }
+    with_handle(handle, |e| copy_buffer(e, out, max_len)).unwrap_or(0)
+}
+
+/// Add a shortcut to an engine instance. See `ime_add_shortcut`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * Both strings must be valid null-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_add_shortcut(
+    handle: *mut Engine,
+    trigger: *const c_char,
+    replacement: *const c_char,
+) {
+    let (Some(trigger_str), Some(replacement_str)) = (c_str(trigger), c_str(replacement)) else {
+        return;
+    };
+    with_handle(handle, |e| add_shortcut(e, trigger_str, replacement_str));
+}
+
+/// Remove a shortcut from an engine instance. See `ime_remove_shortcut`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `trigger` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_remove_shortcut(handle: *mut Engine, trigger: *const c_char) {
+    let Some(trigger_str) = c_str(trigger) else {
+        return;
+    };
+    with_handle(handle, |e| e.shortcuts_mut().remove(trigger_str));
+}
+
+/// Clear all shortcuts of an engine instance.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_clear_shortcuts(handle: *mut Engine) {
+    with_handle(handle, |e| e.shortcuts_mut().clear());
+}
+
+/// Restore the buffer of an engine instance from a word. See `ime_restore_word`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `word` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_restore_word(handle: *mut Engine, word: *const c_char) {
+    let Some(word_str) = c_str(word) else {
+        return;
+    };
+    with_handle(handle, |e| e.restore_word(word_str));
+}
+
+/// Re-enter composition on an engine instance. See `ime_set_surrounding_text`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `text` must be a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_set_surrounding_text(
+    handle: *mut Engine,
+    text: *const c_char,
+    cursor: u32,
+) {
+    let Some(text) = c_str(text) else {
+        return;
+    };
+    with_handle(handle, |e| e.set_surrounding_text(text, cursor as usize));
+}
+
+/// Set the focused app and field type of an engine instance. See `ime_set_context`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `app_id` must be null or a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_set_context(
+    handle: *mut Engine,
+    app_id: *const c_char,
+    purpose: u8,
+) {
+    let app_id = c_str(app_id).unwrap_or("");
+    with_handle(handle, |e| {
+        e.set_context(app_id, InputPurpose::from_u8(purpose))
+    });
+}
+
+/// Import shortcut file contents into an engine instance.
+/// See `ime_shortcuts_import_text`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `text` must be null or a valid null-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_shortcuts_import_text(
+    handle: *mut Engine,
+    text: *const c_char,
+) -> i32 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    with_handle(handle, |e| import_shortcuts(e, text)).unwrap_or(-1)
+}
+
+/// Import a shortcut file into an engine instance. See `ime_shortcuts_import_file`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `path` must be null or a valid null-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_shortcuts_import_file(
+    handle: *mut Engine,
+    path: *const c_char,
+) -> i32 {
+    let Some(text) = c_str(path).and_then(|p| std::fs::read_to_string(p).ok()) else {
+        return -1;
+    };
+    with_handle(handle, |e| import_shortcuts(e, &text)).unwrap_or(-1)
+}
+
+/// Export shortcuts of an engine instance. See `ime_shortcuts_export`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_shortcuts_export(
+    handle: *mut Engine,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    with_handle(handle, |e| copy_text(&e.shortcuts().export(), out, max_len)).unwrap_or(0)
+}
+
+/// Set the `{clipboard}` slot of an engine instance. See `ime_set_clipboard`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `text` must be null or a valid null-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_set_clipboard(handle: *mut Engine, text: *const c_char) {
+    let Some(text) = c_str(text) else {
+        return;
+    };
+    with_handle(handle, |e| e.shortcuts_mut().set_clipboard(text));
+}
+
+/// Set the template time offset of an engine instance. See `ime_set_utc_offset`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_set_utc_offset(handle: *mut Engine, minutes: i32) {
+    with_handle(handle, |e| {
+        e.shortcuts_mut().set_utc_offset_minutes(minutes)
+    });
+}
+
+/// Load options of an engine instance from a config file. See `ime_config_load`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `text` must be null or a valid null-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_config_load(handle: *mut Engine, text: *const c_char) -> i32 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    with_handle(handle, |e| load_config(e, text)).unwrap_or(-1)
+}
+
+/// Write options of an engine instance as a config file. See `ime_config_dump`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_config_dump(
+    handle: *mut Engine,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    with_handle(handle, |e| {
+        copy_text(&e.global_config().to_string(), out, max_len)
+    })
+    .unwrap_or(0)
+}
+
+/// Load the per-app policy of an engine instance. See `ime_policy_load`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `text` must be null or a valid null-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_policy_load(handle: *mut Engine, text: *const c_char) -> i32 {
+    let Some(text) = c_str(text) else {
+        return -1;
+    };
+    with_handle(handle, |e| load_policy(e, text)).unwrap_or(-1)
+}
+
+/// Write the per-app policy of an engine instance. See `ime_policy_dump`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_policy_dump(
+    handle: *mut Engine,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    with_handle(handle, |e| copy_text(&e.policy().to_string(), out, max_len)).unwrap_or(0)
+}
+
+/// Remember one option for an app on an engine instance. See `ime_policy_set`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * Other pointers must be null or valid null-terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_policy_set(
+    handle: *mut Engine,
+    pattern: *const c_char,
+    key: *const c_char,
+    value: *const c_char,
+) -> i32 {
+    let (Some(pattern), Some(key), Some(value)) = (c_str(pattern), c_str(key), c_str(value)) else {
+        return -1;
+    };
+    with_handle(handle, |e| set_policy(e, pattern, key, value)).unwrap_or(-1)
+}
+
+/// Serialize the state of an engine instance. See `ime_snapshot`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_snapshot(
+    handle: *mut Engine,
+    out: *mut u8,
+    max_len: i64,
+) -> i64 {
+    with_handle(handle, |e| copy_snapshot(e, out, max_len)).unwrap_or(0)
+}
+
+/// Restore the state of an engine instance. See `ime_restore`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `data` must be null or point to valid memory of at least `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_restore(
+    handle: *mut Engine,
+    data: *const u8,
+    len: i64,
+) -> bool {
+    with_handle(handle, |e| restore_snapshot(e, data, len)).unwrap_or(false)
+}
+
+/// Start or stop recording on an engine instance. See `ime_record`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_record(handle: *mut Engine, enabled: bool) {
+    with_handle(handle, |e| {
+        if enabled {
+            e.start_recording();
+        } else {
+            e.stop_recording();
+        }
+    });
+}
+
+/// Get the key log of an engine instance. See `ime_recording`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_recording(
+    handle: *mut Engine,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    with_handle(handle, |e| {
+        e.recording().map_or(-1, |log| copy_text(log, out, max_len))
+    })
+    .unwrap_or(-1)
+}
+
+/// Get the auto-accent candidates of an engine instance. See `ime_suggestions`.
+///
+/// # Safety
+/// * `handle` must be null or a live pointer returned by `ime_engine_new`
+/// * `out` must be null or point to valid memory of at least `max_len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_suggestions(
+    handle: *mut Engine,
+    out: *mut c_char,
+    max_len: i64,
+) -> i64 {
+    with_handle(handle, |e| {
+        copy_text(&e.suggestions().join("\n"), out, max_len)
+    })
+    .unwrap_or(0)
+}
+
+/// Accept a candidate on an engine instance. See `ime_accept_suggestion`.
+///
+/// # Safety
+/// `handle` must be null or a live pointer returned by `ime_engine_new`.
+#[no_mangle]
+pub unsafe extern "C" fn ime_engine_accept_suggestion(
+    handle: *mut Engine,
+    index: u32,
+) -> *mut Result {
+    with_handle(handle, |e| into_ffi(e.accept_suggestion(index as usize)))
+        .unwrap_or(std::ptr::null_mut())
+}
+
+// ============================================================
+// Tests
+// ============================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::keys;
+    use serial_test::serial;
     use std::ffi::CString;
 
     #[test]
     #[serial]
-    fn test_ffi_flow() {
+    fn test_ffi_flow() {
+        ime_init();
+        ime_method(0); // Telex
+
+        // Type 'a' + 's' -> á
+        let r1 = ime_key(keys::A, false, false);
+        assert!(!r1.is_null());
+        unsafe { ime_free(r1) };
+
+        let r2 = ime_key(keys::S, false, false);
+        assert!(!r2.is_null());
+        unsafe {
+            assert_eq!((*r2).chars[0], 'á' as u32);
+            ime_free(r2);
+        }
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_add_and_clear() {
+        ime_init();
+        ime_clear_shortcuts(); // Clear any existing shortcuts
+        ime_method(0); // Telex
+
+        // Add a shortcut via FFI
+        let trigger = CString::new("vn").unwrap();
+        let replacement = CString::new("Việt Nam").unwrap();
+
+        unsafe {
+            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+        }
+
+        // Verify shortcut was added by checking engine state
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 1);
+        }
+        drop(guard);
+
+        // Clear all shortcuts
+        ime_clear_shortcuts();
+
+        // Verify shortcuts cleared
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 0);
+        }
+        drop(guard);
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_remove() {
+        ime_init();
+        ime_clear_shortcuts(); // Clear any existing shortcuts
+        ime_method(0); // Telex
+
+        // Add two shortcuts
+        let trigger1 = CString::new("hn").unwrap();
+        let replacement1 = CString::new("Hà Nội").unwrap();
+        let trigger2 = CString::new("hcm").unwrap();
+        let replacement2 = CString::new("Hồ Chí Minh").unwrap();
+
+        unsafe {
+            ime_add_shortcut(trigger1.as_ptr(), replacement1.as_ptr());
+            ime_add_shortcut(trigger2.as_ptr(), replacement2.as_ptr());
+        }
+
+        // Verify both added
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 2);
+        }
+        drop(guard);
+
+        // Remove one shortcut
+        unsafe {
+            ime_remove_shortcut(trigger1.as_ptr());
+        }
+
+        // Verify only one remains
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 1);
+        }
+        drop(guard);
+
+        // Clean up
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_null_safety() {
+        ime_init();
+
+        // Should not crash with null pointers
+        unsafe {
+            ime_add_shortcut(std::ptr::null(), std::ptr::null());
+            ime_remove_shortcut(std::ptr::null());
+        }
+
+        // Engine should still work
+        let r = ime_key(keys::A, false, false);
+        assert!(!r.is_null());
+        unsafe { ime_free(r) };
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_unicode() {
+        ime_init();
+        ime_clear_shortcuts(); // Clear any existing shortcuts
+        ime_method(0);
+
+        // Test with Unicode in both trigger and replacement
+        let trigger = CString::new("tphcm").unwrap();
+        let replacement = CString::new("Thành phố Hồ Chí Minh").unwrap();
+
+        unsafe {
+            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+        }
+
+        // Verify shortcut added with proper UTF-8 handling
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 1);
+        }
+        drop(guard);
+
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_symbol_trigger_immediate() {
+        // Test that symbol-only triggers (like "->") are created as immediate shortcuts
+        ime_init();
+        ime_clear_shortcuts();
+        ime_method(0); // Telex
+
+        // Add arrow shortcut via FFI - should auto-detect as immediate
+        let trigger = CString::new("->").unwrap();
+        let replacement = CString::new("→").unwrap();
+
+        unsafe {
+            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+        }
+
+        // Verify shortcut was added with immediate trigger
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 1);
+            let shortcut = e.shortcuts().lookup("->").unwrap().1;
+            assert_eq!(
+                shortcut.condition,
+                engine::shortcut::TriggerCondition::Immediate,
+                "Symbol-only trigger should be immediate"
+            );
+        }
+        drop(guard);
+
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_ffi_letter_trigger_word_boundary() {
+        // Test that letter triggers (like "vn") are created as word boundary shortcuts
+        ime_init();
+        ime_clear_shortcuts();
+        ime_method(0); // Telex
+
+        // Add abbreviation shortcut via FFI - should be word boundary
+        let trigger = CString::new("vn").unwrap();
+        let replacement = CString::new("Việt Nam").unwrap();
+
+        unsafe {
+            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+        }
+
+        // Verify shortcut was added with word boundary trigger
+        let guard = lock_engine();
+        if let Some(ref e) = *guard {
+            assert_eq!(e.shortcuts().len(), 1);
+            let shortcut = e.shortcuts().lookup("vn").unwrap().1;
+            assert_eq!(
+                shortcut.condition,
+                engine::shortcut::TriggerCondition::OnWordBoundary,
+                "Letter trigger should be word boundary"
+            );
+        }
+        drop(guard);
+
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_restore_word_ffi() {
+        ime_init();
+        ime_method(0); // Telex
+
+        // Restore a Vietnamese word
+        let word = CString::new("việt").unwrap();
+        unsafe {
+            ime_restore_word(word.as_ptr());
+        }
+
+        // Type 's' to add sắc mark - should change ệ to ế
+        // Engine returns replacement for changed portion
+        let r = ime_key(keys::S, false, false);
+        assert!(!r.is_null());
+        unsafe {
+            assert_eq!((*r).action, 1, "Should send replacement");
+            // Engine outputs the modified result
+            assert!((*r).count > 0, "Should have output chars");
+            ime_free(r);
+        }
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_restore_word_ffi_null_safety() {
+        ime_init();
+
+        // Should not crash with null pointer
+        unsafe {
+            ime_restore_word(std::ptr::null());
+        }
+
+        // Engine should still work
+        let r = ime_key(keys::A, false, false);
+        assert!(!r.is_null());
+        unsafe { ime_free(r) };
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_char_ffi() {
+        ime_init();
+        ime_method(0); // Telex
+
+        // Same result as keycodes, independent of the host layout
+        let r1 = ime_char('A' as u32, 0);
+        unsafe { ime_free(r1) };
+        let r2 = ime_char('s' as u32, 0);
+        assert!(!r2.is_null());
+        unsafe {
+            assert_eq!((*r2).action, 1);
+            assert_eq!((*r2).chars[0], 'Á' as u32);
+            ime_free(r2);
+        }
+
+        // Invalid codepoint is treated as an unknown char
+        let r3 = ime_char(0xD800, 0);
+        unsafe {
+            assert_eq!((*r3).action, 0);
+            ime_free(r3);
+        }
+
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_method_load_ffi() {
+        ime_init();
+
+        // VNI-like digits with a different layout: 1=sắc, 6=circumflex
+        let def = CString::new("[marks]\n1 = sac\n[tones]\n6 = \"circumflex a e o\"\n").unwrap();
+        assert_eq!(unsafe { ime_method_load(def.as_ptr()) }, 0);
+
+        for key in [keys::A, keys::N6] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
+        let r = ime_key(keys::N1, false, false);
+        unsafe {
+            assert_eq!((*r).chars[0], 'ấ' as u32);
+            ime_free(r);
+        }
+
+        // Syntax errors report their line; null is rejected
+        let bad = CString::new("[marks]\ns = sac\nq = acute").unwrap();
+        unsafe {
+            assert_eq!(ime_method_load(bad.as_ptr()), 3);
+            assert_eq!(ime_method_load(std::ptr::null()), -1);
+        }
+
+        ime_method(0);
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_output_charset_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_output_charset(3); // VIQR
+
+        unsafe { ime_free(ime_key(keys::A, false, false)) };
+        let r = ime_key(keys::S, false, false);
+        unsafe {
+            assert_eq!((*r).backspace, 1);
+            assert_eq!((*r).count, 2);
+            assert_eq!((*r).chars[0], 'a' as u32);
+            assert_eq!((*r).chars[1], '\'' as u32);
+            ime_free(r);
+        }
+
+        ime_output_charset(0);
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_key_v2_long_shortcut() {
+        ime_init();
+        ime_clear_shortcuts();
+        ime_method(0);
+
+        let replacement = "Trân trọng cảm ơn quý khách đã sử dụng dịch vụ. ".repeat(3);
+        let trigger = CString::new("tt").unwrap();
+        let repl = CString::new(replacement.trim_end()).unwrap();
+        unsafe { ime_add_shortcut(trigger.as_ptr(), repl.as_ptr()) };
+
+        for key in [keys::T, keys::T] {
+            unsafe { ime_free_v2(ime_key_v2(key, false, false, false)) };
+        }
+        let r = ime_key_v2(keys::SPACE, false, false, false);
+        assert!(!r.is_null());
+        unsafe {
+            assert_eq!((*r).action, 1);
+            assert_eq!((*r).backspace, 2);
+            assert_eq!((*r).cursor, -1);
+            let text = std::slice::from_raw_parts((*r).text, (*r).text_len as usize);
+            let text = std::str::from_utf8(text).unwrap();
+            assert_eq!(text, format!("{} ", replacement.trim_end()));
+            assert_eq!((*r).chars_len as usize, text.chars().count());
+            assert_eq!(*(*r).text.add((*r).text_len as usize), 0);
+            ime_free_v2(r);
+        }
+
+        ime_clear_shortcuts();
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_shortcut_template_ffi() {
         ime_init();
-        ime_method(0); // Telex
+        ime_clear_shortcuts();
+        ime_method(0);
 
-        // Type 'a' + 's' -> á
-        let r1 = ime_key(keys::A, false, false);
-        assert!(!r1.is_null());
-        unsafe { ime_free(r1) };
+        let trigger = CString::new("kg").unwrap();
+        let repl = CString::new("Kính gửi {clipboard},{cursor}").unwrap();
+        let clipboard = CString::new("anh Nam").unwrap();
+        unsafe {
+            ime_add_shortcut(trigger.as_ptr(), repl.as_ptr());
+            ime_set_clipboard(clipboard.as_ptr());
+        }
 
-        let r2 = ime_key(keys::S, false, false);
-        assert!(!r2.is_null());
+        for key in [keys::K, keys::G] {
+            unsafe { ime_free_v2(ime_key_v2(key, false, false, false)) };
+        }
+        let r = ime_key_v2(keys::SPACE, false, false, false);
         unsafe {
-            assert_eq!((*r2).chars[0], 'á' as u32);
-            ime_free(r2);
+            let text = std::slice::from_raw_parts((*r).text, (*r).text_len as usize);
+            assert_eq!(std::str::from_utf8(text).unwrap(), "Kính gửi anh Nam, ");
+            // Caret lands before the appended space
+            assert_eq!((*r).cursor, 17);
+            ime_free_v2(r);
         }
 
+        ime_clear_shortcuts();
         ime_clear();
     }
 
     #[test]
     #[serial]
-    fn test_shortcut_ffi_add_and_clear() {
+    fn test_context_ffi() {
         ime_init();
-        ime_clear_shortcuts(); // Clear any existing shortcuts
-        ime_method(0); // Telex
+        ime_clear_shortcuts();
+        ime_method(0);
 
-        // Add a shortcut via FFI
-        let trigger = CString::new("vn").unwrap();
-        let replacement = CString::new("Việt Nam").unwrap();
+        let file = CString::new("#gonhanh-shortcuts version=1\nsig\tThân mến\tapp=com.apple.mail")
+            .unwrap();
+        assert_eq!(unsafe { ime_shortcuts_import_text(file.as_ptr()) }, 0);
+
+        let type_sig = || {
+            for key in [keys::S, keys::I, keys::G] {
+                unsafe { ime_free(ime_key(key, false, false)) };
+            }
+            let r = ime_key(keys::SPACE, false, false);
+            let action = unsafe { (*r).action };
+            unsafe { ime_free(r) };
+            action
+        };
 
-        unsafe {
-            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
-        }
+        let mail = CString::new("com.apple.mail").unwrap();
+        unsafe { ime_set_context(mail.as_ptr(), 0) };
+        assert_eq!(type_sig(), 1);
+        unsafe { ime_set_context(std::ptr::null(), 0) };
+        assert_eq!(type_sig(), 0);
 
-        // Verify shortcut was added by checking engine state
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 1);
+        // Password field: no transforms
+        unsafe { ime_set_context(mail.as_ptr(), 2) };
+        unsafe { ime_free(ime_key(keys::A, false, false)) };
+        let r = ime_key(keys::S, false, false);
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
         }
-        drop(guard);
 
-        // Clear all shortcuts
+        unsafe { ime_set_context(std::ptr::null(), 0) };
         ime_clear_shortcuts();
+        ime_clear();
+    }
 
-        // Verify shortcuts cleared
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 0);
+    #[test]
+    #[serial]
+    fn test_surrounding_text_ffi() {
+        ime_init();
+        ime_method(0);
+
+        // Caret after "tiê" in "học tiêng việt"
+        let text = CString::new("học tiêng việt").unwrap();
+        unsafe { ime_set_surrounding_text(text.as_ptr(), 7) };
+        let r = ime_key(keys::S, false, false);
+        unsafe {
+            assert_eq!((*r).action, 1);
+            assert_eq!((*r).backspace, 1);
+            assert_eq!((*r).chars[0], 'ế' as u32);
+            ime_free(r);
         }
-        drop(guard);
 
-        ime_clear();
+        // Null text is a no-op
+        unsafe { ime_set_surrounding_text(std::ptr::null(), 0) };
+        ime_clear_all();
     }
 
     #[test]
     #[serial]
-    fn test_shortcut_ffi_remove() {
+    fn test_shortcuts_import_export_ffi() {
         ime_init();
-        ime_clear_shortcuts(); // Clear any existing shortcuts
-        ime_method(0); // Telex
+        ime_clear_shortcuts();
 
-        // Add two shortcuts
-        let trigger1 = CString::new("hn").unwrap();
-        let replacement1 = CString::new("Hà Nội").unwrap();
-        let trigger2 = CString::new("hcm").unwrap();
-        let replacement2 = CString::new("Hồ Chí Minh").unwrap();
+        let macros = CString::new(";UniKey macros\nvn:Việt Nam\nko:không\n").unwrap();
+        assert_eq!(unsafe { ime_shortcuts_import_text(macros.as_ptr()) }, 0);
+
+        let path =
+            std::env::temp_dir().join(format!("gonhanh-shortcuts-{}.txt", std::process::id()));
+        std::fs::write(&path, "#gonhanh-shortcuts version=1\n->\t→\timmediate\n").unwrap();
+        let path_arg = CString::new(path.to_str().unwrap()).unwrap();
+        // A path is only read by the file variant
+        assert_eq!(unsafe { ime_shortcuts_import_text(path_arg.as_ptr()) }, 1);
+        assert_eq!(unsafe { ime_shortcuts_import_file(path_arg.as_ptr()) }, 0);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(unsafe { ime_shortcuts_import_file(path_arg.as_ptr()) }, -1);
+
+        let len = unsafe { ime_shortcuts_export(std::ptr::null_mut(), 0) };
+        let mut out = vec![0 as c_char; len as usize + 1];
+        assert_eq!(
+            unsafe { ime_shortcuts_export(out.as_mut_ptr(), len + 1) },
+            len
+        );
+        let text = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert_eq!(
+            text,
+            "#gonhanh-shortcuts version=1\n->\t→\timmediate\nko\tkhông\nvn\tViệt Nam\n"
+        );
+
+        let bad = CString::new("vn:Việt Nam\nbroken\n").unwrap();
+        assert_eq!(unsafe { ime_shortcuts_import_text(bad.as_ptr()) }, 2);
+        assert_eq!(unsafe { ime_shortcuts_import_text(std::ptr::null()) }, -1);
+
+        ime_clear_shortcuts();
+    }
 
+    #[test]
+    #[serial]
+    fn test_config_ffi() {
+        ime_init();
+        let text = CString::new("version = 1\nmethod = \"vni\"\nmodern_tone = false\n").unwrap();
+        assert_eq!(unsafe { ime_config_load(text.as_ptr()) }, 0);
+
+        let len = unsafe { ime_config_dump(std::ptr::null_mut(), 0) };
+        let mut out = vec![0 as c_char; len as usize + 1];
+        assert_eq!(unsafe { ime_config_dump(out.as_mut_ptr(), len + 1) }, len);
+        let dumped = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert!(dumped.contains("method = \"vni\"\n"));
+        assert!(dumped.contains("modern_tone = false\n"));
+
+        // Unknown key: error line, nothing applied
+        let bad = CString::new("method = \"telex\"\nturbo = true\n").unwrap();
+        assert_eq!(unsafe { ime_config_load(bad.as_ptr()) }, 2);
+        assert_eq!(unsafe { ime_config_load(std::ptr::null()) }, -1);
+        let r = ime_key(keys::A, false, false);
+        unsafe { ime_free(r) };
+        let r = ime_key(keys::N1, false, false); // VNI sắc
         unsafe {
-            ime_add_shortcut(trigger1.as_ptr(), replacement1.as_ptr());
-            ime_add_shortcut(trigger2.as_ptr(), replacement2.as_ptr());
+            assert_eq!((*r).chars[0], 'á' as u32);
+            ime_free(r);
         }
 
-        // Verify both added
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 2);
-        }
-        drop(guard);
+        ime_strict_syllables(true);
+        assert_eq!(with_engine(|e| e.config().strict_syllables), Some(true));
+        ime_caret_tracking(true);
+        assert_eq!(with_engine(|e| e.config().caret_tracking), Some(true));
 
-        // Remove one shortcut
+        let defaults = CString::new("").unwrap();
+        assert_eq!(unsafe { ime_config_load(defaults.as_ptr()) }, 0);
+        assert_eq!(with_engine(|e| e.config().strict_syllables), Some(false));
+        ime_clear();
+    }
+
+    #[test]
+    #[serial]
+    fn test_undo_ffi() {
+        ime_init();
+        ime_method(0);
+        for key in [keys::A, keys::S] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
+        let r = ime_undo();
         unsafe {
-            ime_remove_shortcut(trigger1.as_ptr());
+            assert_eq!((*r).action, 1);
+            assert_eq!((*r).backspace, 1);
+            assert_eq!((*r).count, 2);
+            assert_eq!((*r).chars[0], 'a' as u32);
+            assert_eq!((*r).chars[1], 's' as u32);
+            ime_free(r);
+        }
+        let r = ime_undo();
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
         }
+        ime_clear();
+    }
 
-        // Verify only one remains
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 1);
+    #[test]
+    #[serial]
+    fn test_english_word_ffi() {
+        ime_init();
+        ime_method(0);
+        ime_english_auto_restore(true);
+        ime_english_dictionary(true);
+        let word = CString::new("Mix").unwrap();
+        unsafe { ime_add_english_word(word.as_ptr()) };
+
+        for key in [keys::M, keys::I, keys::X] {
+            unsafe { ime_free(ime_key(key, false, false)) };
         }
-        drop(guard);
+        let r = ime_key(keys::SPACE, false, false);
+        unsafe {
+            assert_eq!((*r).action, 1);
+            assert_eq!((*r).backspace, 2);
+            assert_eq!((*r).count, 4);
+            assert_eq!((*r).chars[1], 'i' as u32);
+            assert_eq!((*r).chars[2], 'x' as u32);
+            ime_free(r);
+        }
+        assert_eq!(with_engine(|e| e.config().english_dictionary), Some(true));
 
-        // Clean up
-        ime_clear_shortcuts();
+        unsafe {
+            ime_remove_english_word(word.as_ptr());
+            ime_add_english_word(std::ptr::null());
+        }
+        assert_eq!(with_engine(|e| e.english_words().len()), Some(0));
+        ime_english_auto_restore(false);
+        ime_english_dictionary(false);
         ime_clear();
     }
 
     #[test]
     #[serial]
-    fn test_shortcut_ffi_null_safety() {
+    fn test_policy_ffi() {
         ime_init();
+        ime_method(0);
+        let policy = CString::new("[com.apple.Terminal]\nenabled = false\n").unwrap();
+        assert_eq!(unsafe { ime_policy_load(policy.as_ptr()) }, 0);
+
+        let (word, pattern, key, value) = (
+            CString::new("com.microsoft.Word").unwrap(),
+            CString::new("com.microsoft.*").unwrap(),
+            CString::new("method").unwrap(),
+            CString::new("vni").unwrap(),
+        );
+        unsafe {
+            assert_eq!(
+                ime_policy_set(pattern.as_ptr(), key.as_ptr(), value.as_ptr()),
+                0
+            );
+            assert_eq!(
+                ime_policy_set(pattern.as_ptr(), value.as_ptr(), key.as_ptr()),
+                -1
+            );
+            ime_set_context(word.as_ptr(), 0);
+        }
+        unsafe { ime_free(ime_key(keys::A, false, false)) };
+        let r = ime_key(keys::N1, false, false); // VNI sắc
+        unsafe {
+            assert_eq!((*r).chars[0], 'á' as u32);
+            ime_free(r);
+        }
 
-        // Should not crash with null pointers
+        // Dump: both rules; config dump keeps the global method
+        let len = unsafe { ime_policy_dump(std::ptr::null_mut(), 0) };
+        let mut out = vec![0 as c_char; len as usize + 1];
+        assert_eq!(unsafe { ime_policy_dump(out.as_mut_ptr(), len + 1) }, len);
+        let dumped = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert!(dumped.ends_with("[com.microsoft.*]\nmethod = \"vni\"\n"));
+        let len = unsafe { ime_config_dump(std::ptr::null_mut(), 0) };
+        let mut out = vec![0 as c_char; len as usize + 1];
+        unsafe { ime_config_dump(out.as_mut_ptr(), len + 1) };
+        let dumped = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert!(dumped.contains("method = \"telex\"\n"));
+
+        let bad = CString::new("[x]\nturbo = true").unwrap();
+        assert_eq!(unsafe { ime_policy_load(bad.as_ptr()) }, 2);
+
+        let empty = CString::new("").unwrap();
         unsafe {
-            ime_add_shortcut(std::ptr::null(), std::ptr::null());
-            ime_remove_shortcut(std::ptr::null());
+            ime_policy_load(empty.as_ptr());
+            ime_set_context(std::ptr::null(), 0);
         }
+        ime_clear();
+    }
 
-        // Engine should still work
-        let r = ime_key(keys::A, false, false);
-        assert!(!r.is_null());
-        unsafe { ime_free(r) };
+    #[test]
+    fn test_normalize_text_ffi() {
+        let text = CString::new("Hòa thuận, qủa thuỷ").unwrap();
+        let normalize = |style: u8| unsafe {
+            let len = ime_normalize_text(text.as_ptr(), style, std::ptr::null_mut(), 0);
+            let mut out = vec![0 as c_char; len as usize + 1];
+            assert_eq!(
+                ime_normalize_text(text.as_ptr(), style, out.as_mut_ptr(), len + 1),
+                len
+            );
+            CStr::from_ptr(out.as_ptr()).to_str().unwrap().to_string()
+        };
+        assert_eq!(normalize(1), "Hoà thuận, quả thuỷ");
+        assert_eq!(normalize(0), "Hòa thuận, quả thủy");
+        let r = unsafe { ime_normalize_text(std::ptr::null(), 1, std::ptr::null_mut(), 0) };
+        assert_eq!(r, -1);
+    }
 
-        ime_clear();
+    #[test]
+    fn test_convert_text_ffi() {
+        let convert = |f: unsafe extern "C" fn(*const c_char, u8, *mut c_char, i64) -> i64,
+                       text: &str,
+                       method: u8| unsafe {
+            let text = CString::new(text).unwrap();
+            let len = f(text.as_ptr(), method, std::ptr::null_mut(), 0);
+            let mut out = vec![0 as c_char; len as usize + 1];
+            assert_eq!(f(text.as_ptr(), method, out.as_mut_ptr(), len + 1), len);
+            CStr::from_ptr(out.as_ptr()).to_str().unwrap().to_string()
+        };
+        assert_eq!(convert(ime_compose_text, "tieengs Vieetj", 0), "tiếng Việt");
+        assert_eq!(convert(ime_compose_text, "tie6ng1 Vie6t5", 1), "tiếng Việt");
+        assert_eq!(
+            convert(ime_to_keystrokes, "tiếng Việt", 0),
+            "tieengs Vieetj"
+        );
+        assert_eq!(
+            convert(ime_to_keystrokes, "tiếng Việt", 1),
+            "tie6ng1 Vie6t5"
+        );
+        let r = unsafe { ime_to_keystrokes(std::ptr::null(), 0, std::ptr::null_mut(), 0) };
+        assert_eq!(r, -1);
+        let viqr = CString::new("tiếng Việt").unwrap();
+        let r = unsafe { ime_to_keystrokes(viqr.as_ptr(), 2, std::ptr::null_mut(), 0) };
+        assert_eq!(r, -1);
     }
 
     #[test]
     #[serial]
-    fn test_shortcut_ffi_unicode() {
+    fn test_suggestion_ffi() {
         ime_init();
-        ime_clear_shortcuts(); // Clear any existing shortcuts
-        ime_method(0);
+        ime_auto_accent(true);
+        for key in [keys::D, keys::I, keys::SPACE] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
 
-        // Test with Unicode in both trigger and replacement
-        let trigger = CString::new("tphcm").unwrap();
-        let replacement = CString::new("Thành phố Hồ Chí Minh").unwrap();
+        let len = unsafe { ime_suggestions(std::ptr::null_mut(), 0) };
+        let mut out = vec![0 as c_char; len as usize + 1];
+        assert_eq!(unsafe { ime_suggestions(out.as_mut_ptr(), len + 1) }, len);
+        let text = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert_eq!(text.lines().next(), Some("đi"));
 
+        let r = ime_accept_suggestion(0);
         unsafe {
-            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+            assert_eq!((*r).action, 1);
+            assert_eq!((*r).backspace, 3);
+            assert_eq!((*r).count, 3);
+            assert_eq!((*r).chars[0], 'đ' as u32);
+            ime_free(r);
         }
-
-        // Verify shortcut added with proper UTF-8 handling
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 1);
+        assert_eq!(unsafe { ime_suggestions(std::ptr::null_mut(), 0) }, 0);
+        let r = ime_accept_suggestion(0);
+        unsafe {
+            assert_eq!((*r).action, 0);
+            ime_free(r);
         }
-        drop(guard);
+        ime_auto_accent(false);
+        ime_clear_all();
+    }
 
-        ime_clear_shortcuts();
-        ime_clear();
+    #[test]
+    fn test_fold_ffi() {
+        let text = CString::new("Phở Hà Nội").unwrap();
+        let mut out = [0 as c_char; 32];
+        let len = unsafe { ime_fold_text(text.as_ptr(), out.as_mut_ptr(), 32) };
+        let folded = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert_eq!((len, folded), (10, "pho ha noi"));
+
+        let (mut start, mut end) = (0i64, 0i64);
+        let query = CString::new("HA NOI").unwrap();
+        assert!(unsafe { ime_fold_find(text.as_ptr(), query.as_ptr(), &mut start, &mut end) });
+        assert_eq!((start, end), (6, 15));
+        let query = CString::new("hue").unwrap();
+        assert!(!unsafe { ime_fold_find(text.as_ptr(), query.as_ptr(), &mut start, &mut end) });
+        assert_eq!(
+            unsafe { ime_fold_text(std::ptr::null(), out.as_mut_ptr(), 32) },
+            -1
+        );
     }
 
     #[test]
     #[serial]
-    fn test_shortcut_ffi_symbol_trigger_immediate() {
-        // Test that symbol-only triggers (like "->") are created as immediate shortcuts
+    fn test_snapshot_ffi() {
         ime_init();
-        ime_clear_shortcuts();
-        ime_method(0); // Telex
+        ime_method(1); // VNI
+        for key in [keys::V, keys::I, keys::E, keys::N6] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
 
-        // Add arrow shortcut via FFI - should auto-detect as immediate
-        let trigger = CString::new("->").unwrap();
-        let replacement = CString::new("→").unwrap();
+        let size = unsafe { ime_snapshot(std::ptr::null_mut(), 0) };
+        assert!(size > 0);
+        let mut blob = vec![0u8; size as usize];
+        assert_eq!(unsafe { ime_snapshot(blob.as_mut_ptr(), size) }, size);
 
+        // Another app switches to Telex and clears
+        ime_method(0);
+        ime_clear();
         unsafe {
-            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+            assert!(ime_restore(blob.as_ptr(), size));
+            assert!(!ime_restore(blob.as_ptr(), size - 1));
+            assert!(!ime_restore(std::ptr::null(), 0));
         }
 
-        // Verify shortcut was added with immediate trigger
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 1);
-            let shortcut = e.shortcuts().lookup("->").unwrap().1;
-            assert_eq!(
-                shortcut.condition,
-                engine::shortcut::TriggerCondition::Immediate,
-                "Symbol-only trigger should be immediate"
-            );
+        // Still VNI, still inside "viê"
+        let r = ime_key(keys::N5, false, false);
+        unsafe {
+            assert_eq!((*r).action, 1);
+            assert_eq!((*r).chars[0], 'ệ' as u32);
+            ime_free(r);
         }
-        drop(guard);
-
-        ime_clear_shortcuts();
+        ime_method(0);
         ime_clear();
     }
 
     #[test]
     #[serial]
-    fn test_shortcut_ffi_letter_trigger_word_boundary() {
-        // Test that letter triggers (like "vn") are created as word boundary shortcuts
+    fn test_key_log_ffi() {
         ime_init();
-        ime_clear_shortcuts();
-        ime_method(0); // Telex
+        ime_record(true);
+        ime_method(1);
+        for key in [keys::A, keys::N6] {
+            unsafe { ime_free(ime_key(key, false, false)) };
+        }
+        ime_clear();
 
-        // Add abbreviation shortcut via FFI - should be word boundary
-        let trigger = CString::new("vn").unwrap();
-        let replacement = CString::new("Việt Nam").unwrap();
+        let len = unsafe { ime_recording(std::ptr::null_mut(), 0) };
+        let mut out = vec![0 as c_char; len as usize + 1];
+        assert_eq!(unsafe { ime_recording(out.as_mut_ptr(), len + 1) }, len);
+        let log = unsafe { CStr::from_ptr(out.as_ptr()) }.to_str().unwrap();
+        assert_eq!(
+            log,
+            "#gonhanh-keylog version=1\n\
+             o method = \"vni\"\n\
+             k 0 -\n\
+             k 22 - 1 1 0 \"â\"\n\
+             c\n"
+        );
+
+        ime_record(false);
+        assert_eq!(unsafe { ime_recording(std::ptr::null_mut(), 0) }, -1);
+        ime_method(0);
+    }
+
+    // ============================================================
+    // Multi-instance FFI tests
+    // ============================================================
+
+    fn engine_buffer(h: *mut Engine) -> String {
+        let mut out = [0u32; 64];
+        let len = unsafe { ime_engine_get_buffer(h, out.as_mut_ptr(), out.len() as i64) };
+        out[..len as usize]
+            .iter()
+            .filter_map(|&c| char::from_u32(c))
+            .collect()
+    }
+
+    fn engine_type(h: *mut Engine, keys: &[u16]) {
+        for &k in keys {
+            let r = unsafe { ime_engine_key(h, k, false, false) };
+            assert!(!r.is_null());
+            unsafe { ime_free(r) };
+        }
+    }
 
+    #[test]
+    fn test_engine_handles_are_independent() {
+        let a = ime_engine_new();
+        let b = ime_engine_new();
         unsafe {
-            ime_add_shortcut(trigger.as_ptr(), replacement.as_ptr());
+            ime_engine_method(a, 0); // Telex
+            ime_engine_method(b, 1); // VNI
         }
 
-        // Verify shortcut was added with word boundary trigger
-        let guard = lock_engine();
-        if let Some(ref e) = *guard {
-            assert_eq!(e.shortcuts().len(), 1);
-            let shortcut = e.shortcuts().lookup("vn").unwrap().1;
-            assert_eq!(
-                shortcut.condition,
-                engine::shortcut::TriggerCondition::OnWordBoundary,
-                "Letter trigger should be word boundary"
-            );
+        // Interleave keystrokes: composition must not leak between handles
+        engine_type(a, &[keys::V, keys::I]);
+        engine_type(b, &[keys::N, keys::A]);
+        engine_type(a, &[keys::E, keys::E, keys::J]);
+        engine_type(b, &[keys::N6]);
+
+        assert_eq!(engine_buffer(a), "việ");
+        assert_eq!(engine_buffer(b), "nâ");
+
+        unsafe {
+            ime_engine_clear(a);
         }
-        drop(guard);
+        assert_eq!(engine_buffer(a), "");
+        assert_eq!(engine_buffer(b), "nâ");
 
-        ime_clear_shortcuts();
-        ime_clear();
+        unsafe {
+            ime_engine_free(a);
+            ime_engine_free(b);
+        }
     }
 
     #[test]
     #[serial]
-    fn test_restore_word_ffi() {
+    fn test_engine_handle_independent_of_default() {
         ime_init();
-        ime_method(0); // Telex
+        ime_method(0);
+        ime_enabled(false);
 
-        // Restore a Vietnamese word
-        let word = CString::new("việt").unwrap();
-        unsafe {
-            ime_restore_word(word.as_ptr());
-        }
+        let h = ime_engine_new();
+        engine_type(h, &[keys::A, keys::S]);
+        assert_eq!(engine_buffer(h), "á");
 
-        // Type 's' to add sắc mark - should change ệ to ế
-        // Engine returns replacement for changed portion
+        // Default instance stays disabled and untouched
         let r = ime_key(keys::S, false, false);
         assert!(!r.is_null());
         unsafe {
-            assert_eq!((*r).action, 1, "Should send replacement");
-            // Engine outputs the modified result
-            assert!((*r).count > 0, "Should have output chars");
+            assert_eq!((*r).action, 0);
             ime_free(r);
+            ime_engine_free(h);
         }
 
+        ime_enabled(true);
         ime_clear();
     }
 
     #[test]
-    #[serial]
-    fn test_restore_word_ffi_null_safety() {
-        ime_init();
+    fn test_engine_handle_shortcuts() {
+        let h = ime_engine_new();
+        let other = ime_engine_new();
+        let trigger = CString::new("vn").unwrap();
+        let replacement = CString::new("Việt Nam").unwrap();
 
-        // Should not crash with null pointer
         unsafe {
-            ime_restore_word(std::ptr::null());
-        }
+            ime_engine_add_shortcut(h, trigger.as_ptr(), replacement.as_ptr());
+            assert_eq!((*h).shortcuts().len(), 1);
+            assert_eq!((*other).shortcuts().len(), 0);
 
-        // Engine should still work
-        let r = ime_key(keys::A, false, false);
-        assert!(!r.is_null());
-        unsafe { ime_free(r) };
+            ime_engine_remove_shortcut(h, trigger.as_ptr());
+            assert_eq!((*h).shortcuts().len(), 0);
 
-        ime_clear();
+            ime_engine_free(h);
+            ime_engine_free(other);
+        }
+    }
+
+    #[test]
+    fn test_engine_handle_null_safety() {
+        let null = std::ptr::null_mut();
+        let mut out = [0u32; 8];
+        unsafe {
+            assert!(ime_engine_key(null, keys::A, false, false).is_null());
+            assert!(ime_engine_key_ext(null, keys::A, false, false, false).is_null());
+            assert_eq!(ime_engine_get_buffer(null, out.as_mut_ptr(), 8), 0);
+            ime_engine_method(null, 1);
+            ime_engine_clear_all(null);
+            ime_engine_restore_word(null, std::ptr::null());
+            ime_engine_free(null);
+        }
     }
 }
@@ -0,0 +1,97 @@
+//! Line-based text formats
+//!
+//! Config and policy files, method tables, shortcut files and key logs are
+//! all read one line at a time and report errors by line number. They share
+//! the error type and the `key = value` helpers here:
+//!
+//! ```
+//! use gonhanh_core::lines::{self, LineError};
+//!
+//! let text = "# settings\nmethod = \"vni\"  # comment\n\nturbo\n";
+//! let mut lines = lines::content_lines(text);
+//! assert_eq!(lines.next(), Some((2, "method = \"vni\"")));
+//! assert_eq!(lines::key_value("method = \"vni\""), Ok(("method", "vni")));
+//!
+//! let (line, content) = lines.next().unwrap();
+//! let err = lines::key_value(content).map_err(|reason| LineError { line, reason });
+//! assert_eq!(err.unwrap_err().to_string(), "line 4: expected key = value");
+//! ```
+
+use std::fmt;
+
+/// Error while parsing a line-based format
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    /// 1-based line number (0 = the input as a whole)
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Non-blank lines as (1-based line number, content), comments stripped
+pub fn content_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(idx, raw)| (idx + 1, strip_comment(raw).trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// Drop a trailing `# comment`, ignoring '#' inside quotes
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Split `key = value` into the trimmed key and the unquoted value
+pub fn key_value(line: &str) -> Result<(&str, &str), &'static str> {
+    let (key, value) = line.split_once('=').ok_or("expected key = value")?;
+    Ok((key.trim(), unquote(value)))
+}
+
+/// Trim a value and strip surrounding quotes
+pub fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// Check a format version read from a file against the newest supported one
+pub fn check_version(value: &str, supported: u32) -> Result<(), &'static str> {
+    match value.trim().parse::<u32>() {
+        Ok(v) if v <= supported => Ok(()),
+        Ok(_) => Err("unsupported version"),
+        Err(_) => Err("invalid version"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comments_and_quotes() {
+        assert_eq!(strip_comment("key = \"a#b\" # note"), "key = \"a#b\" ");
+        assert_eq!(
+            key_value("  name =  \"Simple Telex\" "),
+            Ok(("name", "Simple Telex"))
+        );
+        assert_eq!(unquote("\""), "\"");
+        assert_eq!(check_version("1", 1), Ok(()));
+        assert_eq!(check_version("2", 1), Err("unsupported version"));
+        assert_eq!(check_version("x", 1), Err("invalid version"));
+    }
+}
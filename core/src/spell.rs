@@ -0,0 +1,245 @@
+//! Spell check and tone normalization for composed Vietnamese text
+//!
+//! Runs each word through the same rules the engine uses while typing, so
+//! text can be linted without an IME (e.g., docs in CI):
+//!
+//! ```
+//! use gonhanh_core::engine::validation::ValidationResult;
+//! use gonhanh_core::spell;
+//!
+//! let spans = spell::check("Tôi ngĩ là hòa", true);
+//! assert_eq!(spans.len(), 2);
+//! assert_eq!(spans[0].reason, ValidationResult::InvalidSpelling); // "ngĩ" → "nghĩ"
+//! assert_eq!(spans[1].reason, ValidationResult::InvalidTonePlacement); // "hòa" → "hoà"
+//! ```
+//!
+//! `normalize` rewrites tone placement into one style:
+//!
+//! ```
+//! use gonhanh_core::spell;
+//!
+//! assert_eq!(spell::normalize("Hòa thuận, qủa tốt", true), "Hoà thuận, quả tốt");
+//! assert_eq!(spell::normalize("Hoà thuận, thuý", false), "Hòa thuận, thúy");
+//! ```
+//!
+//! Only words with Vietnamese diacritics or đ are checked: plain ASCII words
+//! may be names, code or English. Text must be precomposed (NFC).
+
+use crate::data::chars::{mark, parse_char, to_char, ParsedChar};
+use crate::data::vowel::Phonology;
+use crate::engine::buffer::{Buffer, Char, MAX};
+use crate::engine::validation::{validate, BufferSnapshot, ValidationResult};
+use crate::utils;
+
+/// A word that breaks a rule, as a byte range of the checked text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub reason: ValidationResult,
+}
+
+/// Check every word of `text`
+///
+/// `modern_tone` picks the tone placement to expect, as `Engine::set_modern_tone`
+/// ("hoà" when true, "hòa" when false).
+pub fn check(text: &str, modern_tone: bool) -> Vec<Span> {
+    words(text)
+        .into_iter()
+        .filter_map(|(start, word)| {
+            Some(Span {
+                start,
+                end: start + word.len(),
+                reason: check_word(word, modern_tone)?,
+            })
+        })
+        .collect()
+}
+
+/// Move every tone mark to where `modern_tone` places it
+///
+/// Mixed styles become one ("hòa" ↔ "hoà", "thúy" ↔ "thuý") and misplaced
+/// marks are fixed ("hóa" → "hoá", "qủa" → "quả"). Words that fail
+/// validation or carry several marks are kept as written.
+pub fn normalize(text: &str, modern_tone: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut end = 0;
+    for (start, word) in words(text) {
+        if let Some(fixed) = normalize_word(word, modern_tone) {
+            out.push_str(&text[end..start]);
+            out.push_str(&fixed);
+            end = start + word.len();
+        }
+    }
+    out.push_str(&text[end..]);
+    out
+}
+
+/// Runs of letters with their byte offsets
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut rest = text;
+    let mut offset = 0;
+
+    while let Some(start) = rest.find(char::is_alphabetic) {
+        let len = rest[start..]
+            .find(|c: char| !c.is_alphabetic())
+            .unwrap_or(rest.len() - start);
+        words.push((offset + start, &rest[start..start + len]));
+        offset += start + len;
+        rest = &rest[start + len..];
+    }
+    words
+}
+
+/// Reason `word` is misspelled, None when valid or not checked
+pub fn check_word(word: &str, modern_tone: bool) -> Option<ValidationResult> {
+    let chars: Vec<ParsedChar> = word.chars().map(parse_char).collect::<Option<_>>()?;
+    let vietnamese = chars.iter().any(|c| c.tone != 0 || c.mark != 0 || c.stroke);
+    if !vietnamese {
+        return None;
+    }
+
+    let result = validate_chars(&chars);
+    if !result.is_valid() {
+        return Some(result);
+    }
+
+    let marked: Vec<usize> = (0..chars.len()).filter(|&i| chars[i].mark != 0).collect();
+    match marked[..] {
+        [] => None,
+        [pos] if pos == tone_position(&chars, modern_tone)? => None,
+        _ => Some(ValidationResult::InvalidTonePlacement),
+    }
+}
+
+/// `word` with its mark moved, None when already in place or not fixable
+fn normalize_word(word: &str, modern_tone: bool) -> Option<String> {
+    let chars: Vec<ParsedChar> = word.chars().map(parse_char).collect::<Option<_>>()?;
+    let mut marked = (0..chars.len()).filter(|&i| chars[i].mark != mark::NONE);
+    let (from, None) = (marked.next()?, marked.next()) else {
+        return None;
+    };
+    let to = tone_position(&chars, modern_tone)?;
+    if to == from || !validate_chars(&chars).is_valid() {
+        return None;
+    }
+
+    let mark = chars[from].mark;
+    word.chars()
+        .zip(&chars)
+        .enumerate()
+        .map(|(i, (ch, c))| match i {
+            _ if i == from => to_char(c.key, c.caps, c.tone, mark::NONE),
+            _ if i == to => to_char(c.key, c.caps, c.tone, mark),
+            _ => Some(ch),
+        })
+        .collect()
+}
+
+/// Run the engine's validation rules (marks aside)
+fn validate_chars(chars: &[ParsedChar]) -> ValidationResult {
+    validate(&BufferSnapshot {
+        keys: chars.iter().map(|c| c.key).collect(),
+        tones: chars.iter().map(|c| c.tone).collect(),
+        has_tone_info: true,
+    })
+}
+
+/// Where the engine would put the tone mark
+fn tone_position(chars: &[ParsedChar], modern_tone: bool) -> Option<usize> {
+    if chars.len() > MAX {
+        return None;
+    }
+    let mut buf = Buffer::new();
+    for c in chars {
+        buf.push(Char {
+            key: c.key,
+            caps: c.caps,
+            tone: c.tone,
+            mark: 0,
+            stroke: c.stroke,
+        });
+    }
+    let vowels = utils::collect_vowels(&buf);
+    let last_vowel = vowels.last()?.pos;
+    Some(Phonology::find_tone_position(
+        &vowels,
+        utils::has_final_consonant(&buf, last_vowel),
+        modern_tone,
+        utils::has_qu_initial(&buf),
+        utils::has_gi_initial(&buf),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasons(text: &str, modern_tone: bool) -> Vec<(&str, ValidationResult)> {
+        check(text, modern_tone)
+            .into_iter()
+            .map(|s| (&text[s.start..s.end], s.reason))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_text() {
+        let text = "Gõ Nhanh: bộ gõ tiếng Việt nhanh, nhẹ và miễn phí. Người được giữa khuyết.";
+        assert!(check(text, true).is_empty());
+        assert!(check("Hoà thuận, thuỷ triều", true).is_empty());
+        assert!(check("Hòa thuận, thủy triều", false).is_empty());
+    }
+
+    #[test]
+    fn test_reasons() {
+        use ValidationResult::*;
+        assert_eq!(
+            reasons("Ngĩ đến trường, xưởg", true),
+            [("Ngĩ", InvalidSpelling), ("xưởg", InvalidFinal)]
+        );
+        assert_eq!(reasons("trrường bạo", true), [("trrường", InvalidInitial)]);
+        assert_eq!(
+            reasons("tăi đ", true),
+            [("tăi", InvalidVowelPattern), ("đ", NoVowel)]
+        );
+        assert_eq!(
+            reasons("hòa thủy mía", true),
+            [
+                ("hòa", InvalidTonePlacement),
+                ("thủy", InvalidTonePlacement)
+            ]
+        );
+        assert_eq!(reasons("hoà tiếng", false), [("hoà", InvalidTonePlacement)]);
+        assert_eq!(reasons("áá", true), [("áá", InvalidVowelPattern)]);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mixed = "Hòa bình, hoà thuận. Thủy thuỷ, LÒA xòe: qủa HÓA khỏe";
+        assert_eq!(
+            normalize(mixed, true),
+            "Hoà bình, hoà thuận. Thuỷ thuỷ, LOÀ xoè: quả HOÁ khoẻ"
+        );
+        assert_eq!(
+            normalize(mixed, false),
+            "Hòa bình, hòa thuận. Thủy thủy, LÒA xòe: quả HÓA khỏe"
+        );
+        // Closed syllables and gi/qu initials are the same in both styles
+        for text in ["hoàng tuyết", "giữa quà", "người được"] {
+            assert_eq!(normalize(text, true), text);
+            assert_eq!(normalize(text, false), text);
+        }
+        // Invalid or ambiguous words are kept
+        assert_eq!(normalize("ngĩa áá tăì", true), "ngĩa áá tăì");
+        assert!(check(&normalize(mixed, true), true).is_empty());
+    }
+
+    #[test]
+    fn test_skipped_words() {
+        // Plain ASCII and non-Vietnamese letters
+        assert!(check("string John ngi", true).is_empty());
+        assert!(check("señor 東京", true).is_empty());
+        assert_eq!(check_word("naïve", true), None);
+    }
+}
@@ -87,6 +87,95 @@ pub fn key_to_char_ext(key: u16, caps: bool, shift: bool) -> Option<char> {
     key_to_char(key, caps)
 }
 
+/// Convert a layout-resolved character to (key, caps, shift)
+/// Inverse of `key_to_char_ext`, plus control chars (BS, TAB, CR/LF, ESC).
+/// Returns None for characters with no US-layout key (é, €, ...).
+pub fn char_to_key_ext(c: char) -> Option<(u16, bool, bool)> {
+    if c.is_ascii_alphabetic() {
+        let key = match c.to_ascii_lowercase() {
+            'a' => keys::A,
+            'b' => keys::B,
+            'c' => keys::C,
+            'd' => keys::D,
+            'e' => keys::E,
+            'f' => keys::F,
+            'g' => keys::G,
+            'h' => keys::H,
+            'i' => keys::I,
+            'j' => keys::J,
+            'k' => keys::K,
+            'l' => keys::L,
+            'm' => keys::M,
+            'n' => keys::N,
+            'o' => keys::O,
+            'p' => keys::P,
+            'q' => keys::Q,
+            'r' => keys::R,
+            's' => keys::S,
+            't' => keys::T,
+            'u' => keys::U,
+            'v' => keys::V,
+            'w' => keys::W,
+            'x' => keys::X,
+            'y' => keys::Y,
+            _ => keys::Z,
+        };
+        return Some((key, c.is_ascii_uppercase(), false));
+    }
+
+    let (key, shift) = match c {
+        '0' => (keys::N0, false),
+        '1' => (keys::N1, false),
+        '2' => (keys::N2, false),
+        '3' => (keys::N3, false),
+        '4' => (keys::N4, false),
+        '5' => (keys::N5, false),
+        '6' => (keys::N6, false),
+        '7' => (keys::N7, false),
+        '8' => (keys::N8, false),
+        '9' => (keys::N9, false),
+        ' ' => (keys::SPACE, false),
+        '\t' => (keys::TAB, false),
+        '\r' | '\n' => (keys::RETURN, false),
+        '\x08' | '\x7f' => (keys::DELETE, false),
+        '\x1b' => (keys::ESC, false),
+        '.' => (keys::DOT, false),
+        ',' => (keys::COMMA, false),
+        '/' => (keys::SLASH, false),
+        ';' => (keys::SEMICOLON, false),
+        '\'' => (keys::QUOTE, false),
+        '[' => (keys::LBRACKET, false),
+        ']' => (keys::RBRACKET, false),
+        '\\' => (keys::BACKSLASH, false),
+        '-' => (keys::MINUS, false),
+        '=' => (keys::EQUAL, false),
+        '`' => (keys::BACKQUOTE, false),
+        '!' => (keys::N1, true),
+        '@' => (keys::N2, true),
+        '#' => (keys::N3, true),
+        '$' => (keys::N4, true),
+        '%' => (keys::N5, true),
+        '^' => (keys::N6, true),
+        '&' => (keys::N7, true),
+        '*' => (keys::N8, true),
+        '(' => (keys::N9, true),
+        ')' => (keys::N0, true),
+        '_' => (keys::MINUS, true),
+        '+' => (keys::EQUAL, true),
+        ':' => (keys::SEMICOLON, true),
+        '"' => (keys::QUOTE, true),
+        '<' => (keys::COMMA, true),
+        '>' => (keys::DOT, true),
+        '?' => (keys::SLASH, true),
+        '|' => (keys::BACKSLASH, true),
+        '{' => (keys::LBRACKET, true),
+        '}' => (keys::RBRACKET, true),
+        '~' => (keys::BACKQUOTE, true),
+        _ => return None,
+    };
+    Some((key, false, shift))
+}
+
 /// Collect vowels from buffer with phonological info
 pub fn collect_vowels(buf: &Buffer) -> Vec<Vowel> {
     buf.iter()
@@ -139,6 +228,38 @@ pub fn has_gi_initial(buf: &Buffer) -> bool {
         && third.map(keys::is_vowel).unwrap_or(false)
 }
 
+/// Case-insensitive glob match: `*` = any run of chars, `?` = one char
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Last '*' position in pattern and the text position it was tried at
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    // Let the '*' swallow one more char
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 mod test_utils {
     //! Shared test utilities for inline tests
     //!
@@ -395,6 +516,16 @@ mod test_utils {
         }
     }
 
+    /// Run VIQR test cases
+    pub fn viqr(cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            let mut e = Engine::new();
+            e.set_method(2);
+            let result = type_word(&mut e, input);
+            assert_eq!(result, *expected, "[VIQR] '{}' → '{}'", input, result);
+        }
+    }
+
     /// Run Telex test cases with traditional tone placement (hòa, thúy style)
     pub fn telex_traditional(cases: &[(&str, &str)]) {
         for (input, expected) in cases {
@@ -2,7 +2,7 @@
 //! These tests document expected behavior from user bug reports.
 
 mod common;
-use common::telex;
+use common::{assert_replays, telex};
 use gonhanh_core::engine::Engine;
 use gonhanh_core::utils::type_word;
 
@@ -128,6 +128,19 @@ fn bug6_ddddd_deletes_space() {
     );
 }
 
+/// Same report as a recorded key log (`Engine::start_recording`)
+#[test]
+fn bug6_ddddd_key_log() {
+    let log = "#gonhanh-keylog version=1\n\
+               k 49 -\n\
+               k 2 -\n\
+               k 2 - 1 1 0 \"đ\"\n\
+               k 2 - 1 1 0 \"dd\"\n\
+               k 2 -\n\
+               k 2 -\n";
+    assert_replays(&mut Engine::new(), log);
+}
+
 #[test]
 fn ddddd_behavior() {
     let mut e = Engine::new();
@@ -0,0 +1,95 @@
+//! `gonhanh` command-line tests
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run the binary with `input` on stdin, returning (exit code, stdout)
+fn gonhanh(args: &[&str], input: &str) -> (i32, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gonhanh"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn convert() {
+    let out = gonhanh(&["convert"], "Tooi yeeu tieengs Vieetj.\n");
+    assert_eq!(out, (0, "Tôi yêu tiếng Việt.\n".to_string()));
+    let out = gonhanh(&["convert", "--method", "vni", "--reverse"], "Việt Nam");
+    assert_eq!(out, (0, "Vie6t5 Nam".to_string()));
+    assert_eq!(gonhanh(&["convert", "--method", "dvorak"], "").0, 2);
+    let out = gonhanh(&["convert", "--method", "viqr", "--reverse"], "Việt");
+    assert_eq!(out.0, 2);
+}
+
+#[test]
+fn replay() {
+    let log = "# vieetj\nv\ni\ne\ne\nt\nj\nspace\nD caps\nd\n, \n";
+    let (code, out) = gonhanh(&["replay"], log);
+    assert_eq!(code, 0);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines[3], "e            send bs=1 \"ê\"");
+    assert_eq!(lines[5], "j            send bs=2 \"ệt\"");
+    assert_eq!(lines.last(), Some(&"screen: \"việt Đ,\""));
+
+    assert_eq!(gonhanh(&["replay"], "a hyper\n").0, 1);
+
+    let recorded = "#gonhanh-keylog version=1\nk 0 -\nk 0 - 1 1 0 \"â\"\n";
+    assert_eq!(gonhanh(&["replay"], recorded), (0, String::new()));
+    let changed = recorded.replace('â', "a");
+    let (code, out) = gonhanh(&["replay"], &changed);
+    assert_eq!(code, 1);
+    assert_eq!(out, "line 3: expected [1 1 0 \"a\"], got [1 1 0 \"â\"]\n");
+}
+
+#[test]
+fn validate() {
+    let (code, out) = gonhanh(&["validate"], "Tôi ngĩ\nlà hòa\n");
+    assert_eq!(code, 1);
+    assert_eq!(
+        out,
+        "1:5: ngĩ (InvalidSpelling)\n2:4: hòa (InvalidTonePlacement)\n"
+    );
+    assert_eq!(gonhanh(&["validate", "--traditional"], "là hòa\n").0, 0);
+}
+
+#[test]
+fn shortcuts_roundtrip() {
+    let dir = std::env::temp_dir().join(format!("gonhanh-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let unikey = dir.join("unikey.txt");
+    std::fs::write(&unikey, "; UniKey macros\nbtw:by the way\nvn:Việt Nam\n").unwrap();
+    let unikey = unikey.to_str().unwrap();
+
+    let (code, out) = gonhanh(&["shortcuts", "import", unikey], "");
+    assert_eq!((code, out), (0, format!("{}: 2 shortcuts\n", unikey)));
+
+    let (code, native) = gonhanh(&["shortcuts", "export", unikey], "");
+    assert_eq!(code, 0);
+    assert!(native.ends_with("btw\tby the way\nvn\tViệt Nam\n"));
+    let exported = dir.join("native.txt");
+    std::fs::write(&exported, &native).unwrap();
+    let (_, again) = gonhanh(&["shortcuts", "export", exported.to_str().unwrap()], "");
+    assert_eq!(again, native);
+
+    std::fs::write(&exported, "#gonhanh-shortcuts version=1\nbtw\n").unwrap();
+    assert_eq!(
+        gonhanh(&["shortcuts", "import", exported.to_str().unwrap()], "").0,
+        1
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -8,7 +8,7 @@
 
 // Re-export core test utilities
 pub use gonhanh_core::utils::{
-    telex, telex_auto_capitalize, telex_auto_restore, telex_traditional, type_word, vni,
+    telex, telex_auto_capitalize, telex_auto_restore, telex_traditional, type_word, viqr, vni,
     vni_traditional,
 };
 
@@ -76,3 +76,10 @@ pub fn assert_passthrough(e: &mut Engine, key: u16) {
 pub fn assert_transforms(e: &mut Engine, key: u16) {
     assert_action(e, key, false, false, Action::Send);
 }
+
+/// Assert a recorded key log replays with the same results
+pub fn assert_replays(e: &mut Engine, log: &str) {
+    let mismatches = e.replay(log).expect("invalid key log");
+    let report: Vec<String> = mismatches.iter().map(|m| m.to_string()).collect();
+    assert!(mismatches.is_empty(), "{}", report.join("\n"));
+}
@@ -0,0 +1,77 @@
+//! VIQR Tests - Punctuation-based marks and tones
+
+mod common;
+use common::viqr;
+
+// ============================================================
+// WORDS
+// ============================================================
+
+const VIQR_WORDS: &[(&str, &str)] = &[
+    ("Vie^.t", "Việt"),
+    ("vie^'t", "viết"),
+    ("ddu+o+`ng", "đường"),
+    ("ngu+o+`i", "người"),
+    ("tru+o+'c", "trước"),
+    ("kho^ng", "không"),
+    ("thu+o+ng", "thương"),
+    ("na(m", "năm"),
+    ("DDa^'t", "Đất"),
+    // Mark before tone modifier
+    ("vie.^t", "việt"),
+    // Mark moves to the main vowel as the word grows (modern placement)
+    ("ho`a", "hoà"),
+    ("hoa`", "hoà"),
+];
+
+// ============================================================
+// SENTENCES
+// ============================================================
+
+const VIQR_SENTENCES: &[(&str, &str)] = &[
+    ("To^i ye^u Vie^.t Nam", "Tôi yêu Việt Nam"),
+    (
+        "Xin cha`o, ba.n kho?e kho^ng??",
+        "Xin chào, bạn khoẻ không?",
+    ),
+];
+
+// ============================================================
+// PUNCTUATION & REVERTS
+// ============================================================
+
+const VIQR_PUNCTUATION: &[(&str, &str)] = &[
+    // Double modifier leaves the literal symbol
+    ("a''", "a'"),
+    ("di..", "di."),
+    ("a^^", "a^"),
+    // Nothing can take the modifier: stays punctuation
+    ("next.", "next."),
+    ("b'", "b'"),
+    // Modifier at word start is plain punctuation
+    ("'a", "'a"),
+    ("(a)", "(a)"),
+    // Word ends after a literal symbol
+    ("a''b", "a'b"),
+    // Shifted digit is a symbol, plain digit is not a modifier
+    ("a6", "a6"),
+    // Only "dd" strokes; a third 'd' reverts it
+    ("ddd", "dd"),
+    ("dad", "dad"),
+    ("did", "did"),
+];
+
+#[test]
+fn viqr_words() {
+    viqr(VIQR_WORDS);
+}
+
+#[test]
+fn viqr_sentences() {
+    viqr(VIQR_SENTENCES);
+}
+
+#[test]
+fn viqr_punctuation() {
+    viqr(VIQR_PUNCTUATION);
+}